@@ -1,38 +1,392 @@
 //! SMC client implementation
 
-use crate::{data::SmcData, error::*, keys::key_to_u32};
-use std::{mem::size_of, os::raw::c_void};
+use crate::{
+    data::SmcData,
+    dump::{DumpEntry, StructuredDump},
+    error::*,
+    keys::{
+        cpu_temperature_keys, gpu_temperature_keys, key_to_u32, CoreKind, FanKeys, SmcKey,
+        CURRENT_CPU, CURRENT_GPU, FAN_TEST_MODE, KEY_COUNT,
+    },
+    platform::{detect_platform, model, MachineInfo, Platform},
+    types::{AdapterInfo, Ampere, Celsius, FanMode, FanSpeed, Rpm, SensorReading, Volt, Watt},
+};
+use std::{
+    cell::OnceCell,
+    collections::{BTreeMap, HashMap},
+    ffi::CStr,
+    mem::size_of,
+    ops::Range,
+    os::raw::{c_char, c_void},
+    thread,
+    time::Duration,
+};
+
+/// Pause before retrying a read that failed with a transient error
+const TRANSIENT_RETRY_DELAY: Duration = Duration::from_millis(5);
 
 /// SMC client for raw key reading
 #[derive(Debug)]
 pub struct SmcClient {
     connection: SMCConnection,
+    platform: OnceCell<Option<Platform>>,
 }
 
 impl SmcClient {
     /// Create a new SMC client connection
     pub fn new() -> Result<Self> {
-        let connection = SMCConnection::new()?;
-        Ok(Self { connection })
+        let client = Self::new_raw()?;
+        client.platform();
+        Ok(client)
+    }
+
+    /// Create a new SMC client connection without detecting the platform yet
+    ///
+    /// Detection happens on the first call that needs the platform, so
+    /// one-shot raw reads don't pay for it.
+    pub fn new_raw() -> Result<Self> {
+        Ok(Self::with_connection(SMCConnection::new()?))
+    }
+
+    fn with_connection(connection: SMCConnection) -> Self {
+        Self {
+            connection,
+            platform: OnceCell::new(),
+        }
+    }
+
+    /// Whether the connection still answers, checked with a cheap `#KEY` read
+    ///
+    /// Connections can go stale after the machine sleeps.
+    pub fn is_alive(&mut self) -> bool {
+        self.read_key(KEY_COUNT.key).is_ok()
+    }
+
+    /// Replace the connection with a fresh one, keeping the detected platform
+    pub fn reconnect(&mut self) -> Result<()> {
+        self.connection = SMCConnection::new()?;
+        Ok(())
+    }
+
+    /// Forget the cached key information (data type and size) of every key read so far
+    ///
+    /// Each key's information is looked up once and reused, so repeated reads
+    /// take one SMC call instead of two. It never changes while the machine
+    /// is running, so this is only needed to force fresh lookups.
+    pub fn clear_key_cache(&mut self) {
+        self.connection.key_info.clear();
+    }
+
+    /// Detected platform, `None` if the chip could not be identified
+    pub fn platform(&self) -> Option<Platform> {
+        *self.platform.get_or_init(detect_platform)
+    }
+
+    /// Model identifier and serial number of this machine
+    ///
+    /// The serial is `None` if the IORegistry doesn't hand it out, rather
+    /// than failing the whole call.
+    pub fn machine_info(&self) -> Result<MachineInfo> {
+        let model = model().ok_or(SmcError::NotAvailable)?;
+        Ok(MachineInfo {
+            model,
+            serial: platform_serial_number(),
+        })
+    }
+
+    /// Platform used to select sensor keys
+    ///
+    /// Falls back to `Platform::Unknown`, which only uses keys that don't depend
+    /// on the chip.
+    pub fn key_platform(&self) -> Platform {
+        self.platform().unwrap_or_default()
+    }
+
+    /// CPU temperature keys for this machine's platform
+    pub fn cpu_temperature_keys(&self) -> Vec<&'static SmcKey> {
+        cpu_temperature_keys(self.key_platform())
+    }
+
+    /// GPU temperature keys for this machine's platform
+    pub fn gpu_temperature_keys(&self) -> Vec<&'static SmcKey> {
+        gpu_temperature_keys(self.key_platform())
     }
 
     /// Read a raw SMC key and return parsed data
     pub fn read_key(&mut self, key: &str) -> Result<SmcData> {
+        self.read_with_info(key).map(|(_, data)| data)
+    }
+
+    /// Read a raw SMC key, returning `None` if the key doesn't exist on this machine
+    ///
+    /// Unlike `read_key`, only genuine failures (privileges, IOKit errors,
+    /// undecodable data) are errors, so this is the right call for probing.
+    pub fn try_read_key(&mut self, key: &str) -> Result<Option<SmcData>> {
+        crate::source::read_optional(self, key)
+    }
+
+    /// Read a raw SMC key, failing with `UndecodedType` if its data type isn't understood
+    ///
+    /// `read_key` returns such data as `SmcData::Raw`; this tells it apart
+    /// from keys that decoded fine, which helps when mapping out new keys.
+    pub fn read_key_strict(&mut self, key: &str) -> Result<SmcData> {
+        match self.read_with_info(key)? {
+            (info, SmcData::Raw(_)) => Err(SmcError::UndecodedType {
+                key: key.to_string(),
+                data_type: info.data_type,
+            }),
+            (_, data) => Ok(data),
+        }
+    }
+
+    /// Read a raw SMC key and return its key information along with the parsed data
+    ///
+    /// The key information comes from the same lookup the read needs anyway,
+    /// so this avoids a separate `get_key_info` call.
+    pub fn read_with_info(&mut self, key: &str) -> Result<(SmcKeyInfo, SmcData)> {
         if key.len() != 4 {
             return Err(SmcError::InvalidKey(key.to_string()));
         }
 
         let key_u32 = key_to_u32(key);
-        let result = self.connection.read_key(key_u32)?;
+        let result = match self.connection.read_key(key_u32) {
+            Err(e) if e.is_transient() => {
+                // Retry once so a single hiccup under load doesn't lose the reading
+                thread::sleep(TRANSIENT_RETRY_DELAY);
+                self.connection.read_key(key_u32)?
+            }
+            result => result?,
+        };
         let data_type = u32_to_string(result.data_type);
         
-        SmcData::parse(&result.data[..result.data_size as usize], &data_type)
-            .map_err(|mut e| {
-                if let SmcError::DataError { key: ref mut k, .. } = e {
-                    *k = key.to_string();
+        let data = SmcData::parse(&result.data[..result.data_size as usize], &data_type)
+            .map_err(|e| e.for_key(key))?;
+
+        let info = SmcKeyInfo {
+            key: key.to_string(),
+            data_type,
+            data_size: result.data_size,
+        };
+        Ok((info, data))
+    }
+
+    /// Read the ambient (enclosure) temperature, `None` if the machine has no such sensor
+    pub fn ambient_temperature(&mut self) -> Result<Option<Celsius>> {
+        crate::source::ambient_temperature(self)
+    }
+
+    /// Read a single CPU temperature, preferring the filtered diode over the raw one
+    ///
+    /// `None` on machines without a CPU diode or proximity sensor (Apple
+    /// Silicon); use `labeled_cpu_core_temps` there.
+    pub fn cpu_temperature(&mut self) -> Result<Option<Celsius>> {
+        crate::source::cpu_temperature(self)
+    }
+
+    /// Read the discrete GPU temperature, preferring the die sensor over proximity
+    ///
+    /// `None` on machines without a discrete GPU (Apple Silicon, most
+    /// Intel laptops); use `gpu_temperature_keys` there.
+    pub fn gpu_temperature(&mut self) -> Result<Option<Celsius>> {
+        crate::source::gpu_temperature(self)
+    }
+
+    /// Read the integrated GPU temperature of Intel Macs, `None` if there is none
+    ///
+    /// Dual-GPU machines report this alongside `gpu_temperature`.
+    pub fn integrated_gpu_temperature(&mut self) -> Result<Option<Celsius>> {
+        crate::source::integrated_gpu_temperature(self)
+    }
+
+    /// Read the SSD (NAND) temperature, `None` if the machine has no such sensor
+    pub fn ssd_temperature(&mut self) -> Result<Option<Celsius>> {
+        crate::source::ssd_temperature(self)
+    }
+
+    /// Read the current drawn by the CPU
+    pub fn cpu_current(&mut self) -> Result<Ampere> {
+        let data = self.read_key(CURRENT_CPU.key)?;
+        data.as_current().map_err(|e| e.for_key(CURRENT_CPU.key))
+    }
+
+    /// Read the current drawn by the GPU
+    pub fn gpu_current(&mut self) -> Result<Ampere> {
+        let data = self.read_key(CURRENT_GPU.key)?;
+        data.as_current().map_err(|e| e.for_key(CURRENT_GPU.key))
+    }
+
+    /// Read the connected power adapter, `None` when running on battery
+    pub fn adapter_info(&mut self) -> Result<Option<AdapterInfo>> {
+        crate::source::adapter_info(self)
+    }
+
+    /// Read a known key and convert it according to its data type and scale
+    pub fn read_typed(&mut self, key: &SmcKey) -> Result<SensorReading> {
+        crate::source::read_typed(self, key)
+    }
+
+    /// Read a composite sensor (e.g. "CPU Power (computed)") by name, ignoring case
+    pub fn read_composite(&mut self, name: &str) -> Result<f64> {
+        crate::source::read_composite(self, name)
+    }
+
+    /// Read several raw keys over this connection, in the order given
+    ///
+    /// Missing keys give an `Err` entry instead of aborting the batch.
+    pub fn read_many(&mut self, keys: &[&str]) -> Vec<(String, Result<SmcData>)> {
+        crate::source::read_many(self, keys)
+    }
+
+    /// Read several keys into a map of readings typed by their key prefix
+    pub fn read_many_typed(&mut self, keys: &[&str]) -> BTreeMap<String, Result<SensorReading>> {
+        crate::source::read_many_typed(self, keys)
+    }
+
+    /// Read every known temperature sensor, keyed by sensor name
+    ///
+    /// Sensors this machine doesn't have are skipped.
+    pub fn all_temperatures(&mut self) -> Result<BTreeMap<String, Celsius>> {
+        let platform = self.key_platform();
+        crate::source::all_temperatures(self, platform)
+    }
+
+    /// Read the CPU core temperatures labeled with their core kind and index
+    ///
+    /// Cores whose key cannot be read are skipped.
+    pub fn labeled_cpu_core_temps(&mut self) -> Result<Vec<(CoreKind, u8, Celsius)>> {
+        let platform = self.key_platform();
+        crate::source::labeled_cpu_core_temps(self, platform)
+    }
+
+    /// Read the power monitoring keys, optionally omitting 0.0 W readings
+    pub fn power_sensors(&mut self, skip_zero: bool) -> Vec<(&'static SmcKey, Watt)> {
+        crate::source::power_sensors(self, skip_zero)
+    }
+
+    /// Read the voltage rails this machine has
+    pub fn voltages(&mut self) -> Vec<(&'static SmcKey, Volt)> {
+        crate::source::voltages(self)
+    }
+
+    /// Read the actual, minimum, maximum and target speeds of a fan
+    pub fn fan_speed(&mut self, fan: u8) -> Result<FanSpeed> {
+        let platform = self.key_platform();
+        crate::source::fan_speed(self, platform, fan)
+    }
+
+    /// Read the number of fans the SMC reports
+    pub fn fan_count(&mut self) -> Result<u8> {
+        crate::source::fan_count(self)
+    }
+
+    /// Read the speeds of a fan, checking the index against `fan_count` first
+    ///
+    /// An index past the last fan fails with `NoSuchFan` instead of the
+    /// `KeyNotFound` that reading its keys would give.
+    pub fn fan_speed_by_index(&mut self, fan: u8) -> Result<FanSpeed> {
+        let count = self.fan_count()?;
+        if fan >= count {
+            return Err(SmcError::NoSuchFan { fan, count });
+        }
+        self.fan_speed(fan)
+    }
+
+    /// Write raw bytes to a key
+    ///
+    /// `data` must be exactly as long as the key's data size. Writing needs
+    /// root; without it this fails with `InsufficientPrivileges`.
+    pub fn write_key(&mut self, key: &str, data: &[u8]) -> Result<()> {
+        if key.len() != 4 {
+            return Err(SmcError::InvalidKey(key.to_string()));
+        }
+
+        self.connection.write_key(key_to_u32(key), data)
+    }
+
+    /// Read whether a fan is controlled by the SMC or follows a manual target
+    pub fn fan_mode(&mut self, fan: u8) -> Result<FanMode> {
+        let key = FanKeys::new(fan).mode;
+        let mode = self.read_key(&key)?.as_u64().map_err(|e| e.for_key(&key))?;
+        Ok(if mode == 0 { FanMode::Auto } else { FanMode::Forced })
+    }
+
+    /// Hand a fan to the SMC or switch it to its manual target (requires root)
+    ///
+    /// Apple Silicon Macs only take forced fans in fan test mode (`Ftst`), so
+    /// it is switched on before forcing a fan and off again once every fan
+    /// is back under automatic control. Intel Macs don't have the key.
+    pub fn set_fan_mode(&mut self, fan: u8, mode: FanMode) -> Result<()> {
+        let key = FanKeys::new(fan).mode;
+        let info = self.get_key_info(&key)?;
+        if info.data_size != 1 {
+            return Err(SmcError::UnknownDataType(info.data_type));
+        }
+        let test_mode = crate::source::read_optional(self, FAN_TEST_MODE.key)?.is_some();
+
+        match mode {
+            FanMode::Auto => {
+                self.write_key(&key, &[0])?;
+                if test_mode && !self.any_fan_forced()? {
+                    self.write_key(FAN_TEST_MODE.key, &[0])?;
                 }
-                e
-            })
+                Ok(())
+            }
+            FanMode::Forced => {
+                if test_mode {
+                    self.write_key(FAN_TEST_MODE.key, &[1])?;
+                }
+                self.write_key(&key, &[1])
+            }
+        }
+    }
+
+    fn any_fan_forced(&mut self) -> Result<bool> {
+        for fan in 0..self.fan_count()? {
+            if self.fan_mode(fan)? == FanMode::Forced {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Set the target speed of a fan (requires root)
+    ///
+    /// The target must lie within the fan's minimum and maximum speed. The
+    /// fan only follows it in `FanMode::Forced`.
+    pub fn set_fan_target(&mut self, fan: u8, rpm: Rpm) -> Result<()> {
+        let speed = self.fan_speed(fan)?;
+        let key = FanKeys::new(fan).target;
+        if !(speed.min.0..=speed.max.0).contains(&rpm.0) {
+            return Err(SmcError::OutOfRange {
+                key,
+                value: rpm.0,
+                min: speed.min.0,
+                max: speed.max.0,
+            });
+        }
+
+        let info = self.get_key_info(&key)?;
+        match SmcData::Float(rpm.0).to_smc_bytes(&info.data_type) {
+            Some(data) => self.write_key(&key, &data),
+            None => Err(SmcError::UnknownDataType(info.data_type)),
+        }
+    }
+
+    /// Set the target of a fan within its speed range (requires root)
+    ///
+    /// 0% is the fan's minimum speed and 100% its maximum.
+    pub fn set_fan_target_percentage(&mut self, fan: u8, pct: f32) -> Result<()> {
+        if !(0.0..=100.0).contains(&pct) {
+            return Err(SmcError::OutOfRange {
+                key: FanKeys::new(fan).target,
+                value: pct,
+                min: 0.0,
+                max: 100.0,
+            });
+        }
+
+        let speed = self.fan_speed(fan)?;
+        self.set_fan_target(fan, speed.rpm_at(pct))
     }
 
     /// Get key information without reading data
@@ -53,7 +407,7 @@ impl SmcClient {
 
     /// Get the total number of SMC keys
     pub fn get_key_count(&mut self) -> Result<u32> {
-        let data = self.read_key("#KEY")?;
+        let data = self.read_key(KEY_COUNT.key)?;
         data.as_u64().map(|v| v as u32)
     }
 
@@ -63,8 +417,73 @@ impl SmcClient {
         let mut keys = Vec::new();
 
         for i in 0..count {
-            if let Ok(info) = self.connection.get_key_by_index(i) {
-                keys.push(u32_to_string(info.key));
+            if let Ok(key) = self.connection.get_key_by_index(i) {
+                keys.push(u32_to_string(key));
+            }
+        }
+
+        Ok(keys)
+    }
+
+    /// Read all keys and group them by inferred kind (warning: slow operation)
+    ///
+    /// Keys that cannot be read are left out.
+    pub fn structured_dump(&mut self) -> Result<StructuredDump> {
+        let count = self.get_key_count()?;
+        let mut dump = StructuredDump::default();
+
+        for i in 0..count {
+            let key = match self.connection.get_key_by_index(i) {
+                Ok(key) => u32_to_string(key),
+                Err(_) => continue,
+            };
+            if let Ok((info, value)) = self.read_with_info(&key) {
+                dump.insert(DumpEntry {
+                    key,
+                    data_type: info.data_type,
+                    value,
+                });
+            }
+        }
+
+        Ok(dump)
+    }
+
+    /// Get information for a window of `limit` keys starting at index `offset`
+    ///
+    /// An `offset` past the last key yields an empty list. As in `list_all_keys`,
+    /// indices whose key information can't be read are skipped, so a page may
+    /// hold fewer than `limit` keys even before the end.
+    pub fn keys_page(&mut self, offset: u32, limit: u32) -> Result<Vec<SmcKeyInfo>> {
+        let count = self.get_key_count()?;
+        let mut keys = Vec::new();
+
+        for i in page_range(offset, limit, count) {
+            if let Ok(info) = self.key_info_at(i) {
+                keys.push(info);
+            }
+        }
+
+        Ok(keys)
+    }
+
+    // The index reply only names the key, its type and size need a lookup of their own
+    fn key_info_at(&mut self, index: u32) -> Result<SmcKeyInfo> {
+        let key = self.connection.get_key_by_index(index)?;
+        self.get_key_info(&u32_to_string(key))
+    }
+
+    /// Get information for all keys of an SMC data type, e.g. `"fpe2"` (warning: slow operation)
+    ///
+    /// Trailing spaces are ignored, so `"flt"` matches `"flt "` as well.
+    pub fn keys_of_type(&mut self, data_type: &str) -> Result<Vec<SmcKeyInfo>> {
+        let count = self.get_key_count()?;
+        let mut keys = Vec::new();
+
+        for i in 0..count {
+            match self.key_info_at(i) {
+                Ok(info) if has_type(&info, data_type) => keys.push(info),
+                _ => {}
             }
         }
 
@@ -74,6 +493,7 @@ impl SmcClient {
 
 /// SMC key information
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SmcKeyInfo {
     /// The SMC key
     pub key: String,
@@ -83,6 +503,17 @@ pub struct SmcKeyInfo {
     pub data_size: u32,
 }
 
+// Whether a key is of `data_type`, ignoring trailing spaces on either side
+fn has_type(info: &SmcKeyInfo, data_type: &str) -> bool {
+    info.data_type.trim_end() == data_type.trim_end()
+}
+
+// Indices of a page of keys, empty if `offset` lies past the last key
+fn page_range(offset: u32, limit: u32, count: u32) -> Range<u32> {
+    let end = offset.saturating_add(limit).min(count);
+    offset.min(end)..end
+}
+
 fn u32_to_string(value: u32) -> String {
     let bytes = value.to_be_bytes();
     String::from_utf8_lossy(&bytes).to_string()
@@ -93,6 +524,8 @@ fn u32_to_string(value: u32) -> String {
 #[derive(Debug)]
 struct SMCConnection {
     connection: io_connect_t,
+    // Data type and size by key, so reads and writes skip the info call
+    key_info: HashMap<u32, SmcKeyInfo2>,
 }
 
 impl Drop for SMCConnection {
@@ -106,18 +539,32 @@ impl Drop for SMCConnection {
 impl SMCConnection {
     fn new() -> Result<Self> {
         let connection = unsafe { smc_open()? };
-        Ok(Self { connection })
+        Ok(Self {
+            connection,
+            key_info: HashMap::new(),
+        })
     }
 
     fn read_key(&mut self, key: u32) -> Result<SmcResult> {
-        unsafe { smc_read_key(self.connection, key) }
+        let info = self.get_key_info(key)?;
+        unsafe { smc_read_key(self.connection, &info) }
     }
 
     fn get_key_info(&mut self, key: u32) -> Result<SmcKeyInfo2> {
-        unsafe { smc_get_key_info(self.connection, key) }
+        if let Some(info) = self.key_info.get(&key) {
+            return Ok(info.clone());
+        }
+        let info = unsafe { smc_get_key_info(self.connection, key)? };
+        self.key_info.insert(key, info.clone());
+        Ok(info)
+    }
+
+    fn write_key(&mut self, key: u32, data: &[u8]) -> Result<()> {
+        let info = self.get_key_info(key)?;
+        unsafe { smc_write_key(self.connection, &info, data) }
     }
 
-    fn get_key_by_index(&mut self, index: u32) -> Result<SmcKeyInfo2> {
+    fn get_key_by_index(&mut self, index: u32) -> Result<u32> {
         unsafe { smc_get_key_by_index(self.connection, index) }
     }
 }
@@ -129,7 +576,7 @@ struct SmcResult {
     data: [u8; 32],
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct SmcKeyInfo2 {
     key: u32,
     data_type: u32,
@@ -227,9 +674,74 @@ extern "C" {
         outputSize: *mut usize,
     ) -> kern_return_t;
     fn IOObjectRelease(object: io_service_t) -> kern_return_t;
+    fn IORegistryEntryCreateCFProperty(
+        entry: io_service_t,
+        key: *const c_void,
+        allocator: *const c_void,
+        options: u32,
+    ) -> *const c_void;
     fn mach_task_self() -> mach_port_t;
 }
 
+const K_CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFStringCreateWithCString(
+        allocator: *const c_void,
+        c_str: *const c_char,
+        encoding: u32,
+    ) -> *const c_void;
+    fn CFStringGetCString(
+        string: *const c_void,
+        buffer: *mut c_char,
+        buffer_size: isize,
+        encoding: u32,
+    ) -> u8;
+    fn CFGetTypeID(cf: *const c_void) -> usize;
+    fn CFStringGetTypeID() -> usize;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// Serial number from the IORegistry's platform expert, `None` if it can't be read
+fn platform_serial_number() -> Option<String> {
+    unsafe {
+        let matching_dict = IOServiceMatching(b"IOPlatformExpertDevice\0".as_ptr());
+        let service = IOServiceGetMatchingService(std::ptr::null_mut(), matching_dict);
+        if service.is_null() {
+            return None;
+        }
+
+        let key = CFStringCreateWithCString(
+            std::ptr::null(),
+            b"IOPlatformSerialNumber\0".as_ptr() as *const c_char,
+            K_CF_STRING_ENCODING_UTF8,
+        );
+        let value = IORegistryEntryCreateCFProperty(service, key, std::ptr::null(), 0);
+        CFRelease(key);
+        IOObjectRelease(service);
+        if value.is_null() {
+            return None;
+        }
+
+        let mut buf = [0 as c_char; 64];
+        let copied = CFGetTypeID(value) == CFStringGetTypeID()
+            && CFStringGetCString(
+                value,
+                buf.as_mut_ptr(),
+                buf.len() as isize,
+                K_CF_STRING_ENCODING_UTF8,
+            ) != 0;
+        CFRelease(value);
+        if !copied {
+            return None;
+        }
+
+        let serial = CStr::from_ptr(buf.as_ptr()).to_string_lossy().trim().to_string();
+        Some(serial).filter(|serial| !serial.is_empty())
+    }
+}
+
 unsafe fn smc_open() -> Result<io_connect_t> {
     let matching_dict = IOServiceMatching(b"AppleSMC\0".as_ptr());
     let service = IOServiceGetMatchingService(std::ptr::null_mut(), matching_dict);
@@ -243,44 +755,77 @@ unsafe fn smc_open() -> Result<io_connect_t> {
     IOObjectRelease(service);
 
     if result != KERN_SUCCESS {
-        return Err(SmcError::SmcError(result));
+        return Err(open_error(result));
     }
 
     Ok(connection)
 }
 
-unsafe fn smc_read_key(connection: io_connect_t, key: u32) -> Result<SmcResult> {
-    // First get key info
-    let mut input = SMCKeyData::default();
-    input.key = key;
-    input.data8 = 9; // SMC_CMD_READ_KEYINFO
-
-    let mut output = SMCKeyData::default();
-    smc_call(connection, &input, &mut output)?;
-
-    let data_type = output.keyInfo.dataType;
-    let data_size = output.keyInfo.dataSize;
+// Well-known codes (missing privileges, no device) keep their own variant;
+// anything else most likely means another process holds the SMC
+fn open_error(code: kern_return_t) -> SmcError {
+    match SmcError::from_io_return(code) {
+        SmcError::SmcError(code) => SmcError::Busy(code),
+        e => e,
+    }
+}
 
-    if data_size > 32 {
+// Reading and writing need the key's info (see `SMCConnection::get_key_info`)
+unsafe fn smc_read_key(connection: io_connect_t, info: &SmcKeyInfo2) -> Result<SmcResult> {
+    let input = SMCKeyData {
+        key: info.key,
+        keyInfo: SMCKeyDataKeyInfo {
+            dataSize: info.data_size,
+            ..Default::default()
+        },
+        data8: 5, // SMC_CMD_READ_BYTES
+        ..Default::default()
+    };
+
+    if info.data_size as usize > input.bytes.len() {
         return Err(SmcError::DataError {
-            key: crate::keys::u32_to_key(key),
-            data_type: u32_to_string(data_type),
+            key: crate::keys::u32_to_key(info.key),
+            data_type: u32_to_string(info.data_type),
         });
     }
 
-    // Now read the actual data
-    input.keyInfo.dataSize = data_size;
-    input.data8 = 5; // SMC_CMD_READ_BYTES
-
+    let mut output = SMCKeyData::default();
     smc_call(connection, &input, &mut output)?;
 
     Ok(SmcResult {
-        data_type,
-        data_size,
+        data_type: info.data_type,
+        data_size: info.data_size,
         data: output.bytes,
     })
 }
 
+unsafe fn smc_write_key(connection: io_connect_t, info: &SmcKeyInfo2, data: &[u8]) -> Result<()> {
+    let mut input = SMCKeyData {
+        key: info.key,
+        keyInfo: SMCKeyDataKeyInfo {
+            dataSize: info.data_size,
+            ..Default::default()
+        },
+        data8: 6, // SMC_CMD_WRITE_BYTES
+        ..Default::default()
+    };
+
+    if data.len() != info.data_size as usize || data.len() > input.bytes.len() {
+        return Err(SmcError::DataError {
+            key: crate::keys::u32_to_key(info.key),
+            data_type: u32_to_string(info.data_type),
+        });
+    }
+    input.bytes[..data.len()].copy_from_slice(data);
+
+    let mut output = SMCKeyData::default();
+    smc_call(connection, &input, &mut output)?;
+    match output.result {
+        0 => Ok(()),
+        result => Err(SmcError::SmcError(result as i32)),
+    }
+}
+
 unsafe fn smc_get_key_info(connection: io_connect_t, key: u32) -> Result<SmcKeyInfo2> {
     let mut input = SMCKeyData::default();
     input.key = key;
@@ -296,7 +841,8 @@ unsafe fn smc_get_key_info(connection: io_connect_t, key: u32) -> Result<SmcKeyI
     })
 }
 
-unsafe fn smc_get_key_by_index(connection: io_connect_t, index: u32) -> Result<SmcKeyInfo2> {
+// The reply only fills in the key; its type and size need `smc_get_key_info`
+unsafe fn smc_get_key_by_index(connection: io_connect_t, index: u32) -> Result<u32> {
     let mut input = SMCKeyData::default();
     input.data8 = 8; // SMC_CMD_READ_INDEX
     input.data32 = index;
@@ -304,11 +850,7 @@ unsafe fn smc_get_key_by_index(connection: io_connect_t, index: u32) -> Result<S
     let mut output = SMCKeyData::default();
     smc_call(connection, &input, &mut output)?;
 
-    Ok(SmcKeyInfo2 {
-        key: output.key,
-        data_type: output.keyInfo.dataType,
-        data_size: output.keyInfo.dataSize,
-    })
+    Ok(output.key)
 }
 
 unsafe fn smc_call(
@@ -330,13 +872,93 @@ unsafe fn smc_call(
     match result {
         KERN_SUCCESS => {
             if output.result == 132 {
-                // Key not found
-                Err(SmcError::InvalidKey("key not found".to_string()))
+                Err(SmcError::KeyNotFound(crate::keys::u32_to_key(input.key)))
             } else {
                 Ok(())
             }
         }
         RETURN_NOT_PRIVILEGED => Err(SmcError::InsufficientPrivileges),
-        _ => Err(SmcError::SmcError(result)),
+        _ => Err(SmcError::from_io_return(result)),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_error_keeps_well_known_codes() {
+        let not_privileged = 0xe00002c1_u32 as i32;
+        assert!(matches!(open_error(not_privileged), SmcError::InsufficientPrivileges));
+        assert!(!open_error(not_privileged).is_transient());
+    }
+
+    #[test]
+    fn open_error_reports_other_codes_as_busy() {
+        let busy = 0xe00002d5_u32 as i32;
+        assert!(matches!(open_error(busy), SmcError::Busy(code) if code == busy));
+        assert!(open_error(busy).is_transient());
+    }
+
+    #[test]
+    fn page_within_the_keys() {
+        assert_eq!(page_range(10, 5, 100), 10..15);
+        assert_eq!(page_range(0, 100, 100), 0..100);
+    }
+
+    #[test]
+    fn page_is_cut_at_the_last_key() {
+        assert_eq!(page_range(95, 10, 100), 95..100);
+        assert_eq!(page_range(90, u32::MAX, 100), 90..100);
+    }
+
+    #[test]
+    fn page_past_the_last_key_is_empty() {
+        assert!(page_range(100, 5, 100).is_empty());
+        assert!(page_range(150, 5, 100).is_empty());
+        assert!(page_range(u32::MAX, u32::MAX, 100).is_empty());
+    }
+
+    fn key_info(key: &str, data_type: &str, data_size: u32) -> SmcKeyInfo {
+        SmcKeyInfo {
+            key: key.to_string(),
+            data_type: data_type.to_string(),
+            data_size,
+        }
+    }
+
+    #[test]
+    fn only_keys_of_the_requested_type_match() {
+        let keys = [
+            key_info("TC0P", "flt ", 4),
+            key_info("FNum", "ui8 ", 1),
+            key_info("F0Ac", "fpe2", 2),
+            key_info("Tp01", "flt ", 4),
+            key_info("FNam", "{fds", 16),
+        ];
+        let matching = |data_type: &str| -> Vec<&str> {
+            keys.iter()
+                .filter(|info| has_type(info, data_type))
+                .map(|info| info.key.as_str())
+                .collect()
+        };
+
+        assert_eq!(matching("flt"), ["TC0P", "Tp01"]);
+        assert_eq!(matching("flt "), ["TC0P", "Tp01"]);
+        assert_eq!(matching("fpe2"), ["F0Ac"]);
+        assert!(matching("sp78").is_empty());
+    }
+
+    #[test]
+    fn platform_is_detected_on_first_use() {
+        let client = SmcClient::with_connection(SMCConnection {
+            connection: std::ptr::null_mut(),
+            key_info: HashMap::new(),
+        });
+        assert!(client.platform.get().is_none());
+
+        let platform = client.key_platform();
+        let detected = *client.platform.get().expect("detected by key_platform");
+        assert_eq!(platform, detected.unwrap_or_default());
+    }
+}
@@ -1,7 +1,10 @@
 //! SMC data parsing and conversion
 
 use crate::{error::*, types::*};
-use std::{convert::TryInto, ffi::CStr};
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt,
+};
 
 /// Raw SMC data value
 #[derive(Debug, Clone)]
@@ -25,36 +28,22 @@ impl SmcData {
     pub fn parse(data: &[u8], data_type: &str) -> Result<Self> {
         match data_type {
             "flag" => Ok(SmcData::Flag(!data.is_empty() && data[0] != 0)),
-            "flt " => {
-                if data.len() >= 4 {
-                    let bytes: [u8; 4] = data[..4].try_into().map_err(|_| {
-                        SmcError::DataError {
-                            key: "unknown".to_string(),
-                            data_type: data_type.to_string(),
-                        }
-                    })?;
-                    // SMC floats are stored in little-endian format on modern Macs
-                    Ok(SmcData::Float(f32::from_le_bytes(bytes)))
-                } else {
-                    Err(SmcError::DataError {
-                        key: "unknown".to_string(),
-                        data_type: data_type.to_string(),
-                    })
-                }
-            }
+            "flt " => match decode_flt(data) {
+                Some(value) => Ok(SmcData::Float(value)),
+                None => Err(SmcError::DataError {
+                    key: "unknown".to_string(),
+                    data_type: data_type.to_string(),
+                }),
+            },
             "ch8*" => {
-                let s = if data.contains(&0) {
-                    unsafe { CStr::from_ptr(data.as_ptr() as *const _) }
-                        .to_string_lossy()
-                        .into_owned()
-                } else {
-                    let mut data = data.to_vec();
-                    data.push(0);
-                    unsafe { CStr::from_ptr(data.as_ptr() as *const _) }
-                        .to_string_lossy()
-                        .into_owned()
-                };
-                Ok(SmcData::String(s))
+                // Fixed-width string fields are NUL padded and may contain
+                // non-printable bytes; stop at the first NUL and blank out the rest
+                let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+                let s: String = String::from_utf8_lossy(&data[..end])
+                    .chars()
+                    .map(|c| if c.is_control() { ' ' } else { c })
+                    .collect();
+                Ok(SmcData::String(s.trim_end().to_string()))
             }
             t if t.starts_with("ui") => {
                 match &t[2..] {
@@ -116,10 +105,102 @@ impl SmcData {
                     Ok(SmcData::Raw(data.to_vec()))
                 }
             }
+            "{lim" | "{ala" => match decode_limit(data) {
+                Some(limit) => Ok(SmcData::Float(limit)),
+                None => Ok(SmcData::Raw(data.to_vec())),
+            },
             _ => Ok(SmcData::Raw(data.to_vec())),
         }
     }
 
+    /// Encode the value as raw SMC bytes of the given SMC data type, reversing `parse`
+    ///
+    /// Returns `None` if the type isn't supported for writing or the value
+    /// doesn't fit it (e.g. a negative value for `ui8 `, or a fractional
+    /// value for an integer type).
+    ///
+    /// ```
+    /// # use macsmc::SmcData;
+    /// let bytes = SmcData::Float(1200.0).to_smc_bytes("fpe2").unwrap();
+    /// assert_eq!(bytes, vec![0x12, 0xc0]);
+    /// let value = SmcData::parse(&bytes, "fpe2").unwrap().as_f64().unwrap();
+    /// assert_eq!(value, 1200.0);
+    ///
+    /// let bytes = SmcData::Int(-42).to_smc_bytes("si16").unwrap();
+    /// assert_eq!(SmcData::parse(&bytes, "si16").unwrap().as_f64(), Some(-42.0));
+    /// assert_eq!(SmcData::Int(-1).to_smc_bytes("ui8 "), None);
+    /// ```
+    pub fn to_smc_bytes(&self, data_type: &str) -> Option<Vec<u8>> {
+        let integer = match self {
+            SmcData::Flag(b) => Some(*b as i128),
+            SmcData::Int(i) => Some(*i as i128),
+            SmcData::Uint(u) => Some(*u as i128),
+            SmcData::Float(f) if f.fract() == 0.0 => Some(*f as i128),
+            _ => None,
+        };
+
+        match data_type {
+            "flag" => self.as_bool().ok().map(|b| vec![b as u8]),
+            "flt " => self.as_f64().map(|f| (f as f32).to_le_bytes().to_vec()),
+            t if t.starts_with("ui") => {
+                let integer = integer?;
+                match &t[2..] {
+                    "8 " => u8::try_from(integer).ok().map(|v| vec![v]),
+                    "16" => u16::try_from(integer).ok().map(|v| v.to_be_bytes().to_vec()),
+                    "32" => u32::try_from(integer).ok().map(|v| v.to_be_bytes().to_vec()),
+                    "64" => u64::try_from(integer).ok().map(|v| v.to_be_bytes().to_vec()),
+                    _ => None,
+                }
+            }
+            t if t.starts_with("si") => {
+                let integer = integer?;
+                match &t[2..] {
+                    "8 " => i8::try_from(integer).ok().map(|v| v.to_be_bytes().to_vec()),
+                    "16" => i16::try_from(integer).ok().map(|v| v.to_be_bytes().to_vec()),
+                    "32" => i32::try_from(integer).ok().map(|v| v.to_be_bytes().to_vec()),
+                    "64" => i64::try_from(integer).ok().map(|v| v.to_be_bytes().to_vec()),
+                    _ => None,
+                }
+            }
+            t if (t.starts_with("fp") || t.starts_with("sp")) && t.len() == 4 => {
+                let f_bits = (t.as_bytes()[3] as char).to_digit(16)?;
+                let raw = (self.as_f64()? * (1 << f_bits) as f64).round();
+                if t.starts_with("fp") {
+                    let raw = (0.0..=u16::MAX as f64).contains(&raw).then_some(raw as u16)?;
+                    Some(raw.to_be_bytes().to_vec())
+                } else {
+                    let raw = (i16::MIN as f64..=i16::MAX as f64)
+                        .contains(&raw)
+                        .then_some(raw as i16)?;
+                    Some(raw.to_be_bytes().to_vec())
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Multiply a numeric value by `scale`, giving a float in the scaled unit
+    ///
+    /// A scale of `1.0` and non-numeric values are left unchanged.
+    ///
+    /// ```
+    /// # use macsmc::{Celsius, SmcData};
+    /// // Deci-degree temperature key
+    /// let temp = SmcData::Uint(425).scaled(0.1).as_temperature().unwrap();
+    /// assert_eq!(temp, Celsius(42.5));
+    /// ```
+    pub fn scaled(self, scale: f32) -> Self {
+        if scale == 1.0 {
+            return self;
+        }
+        match self {
+            SmcData::Float(f) => SmcData::Float(f * scale),
+            SmcData::Int(i) => SmcData::Float(i as f32 * scale),
+            SmcData::Uint(u) => SmcData::Float(u as f32 * scale),
+            other => other,
+        }
+    }
+
     /// Convert to temperature (Celsius)
     pub fn as_temperature(&self) -> Result<Celsius> {
         match self {
@@ -198,6 +279,21 @@ impl SmcData {
         }
     }
 
+    /// Convert a PWM duty cycle to percentage
+    ///
+    /// Unsigned integers are `ui8` PWM values and are scaled from 0-255 to
+    /// 0-100%. Floats are already percentages and are taken as is.
+    pub fn as_pwm_percentage(&self) -> Result<Percentage> {
+        match self {
+            SmcData::Float(f) => Ok(Percentage(*f)),
+            SmcData::Uint(u) if *u <= 255 => Ok(Percentage(*u as f32 * 100.0 / 255.0)),
+            _ => Err(SmcError::DataError {
+                key: "unknown".to_string(),
+                data_type: "pwm percentage".to_string(),
+            }),
+        }
+    }
+
     /// Convert to boolean
     pub fn as_bool(&self) -> Result<bool> {
         match self {
@@ -223,6 +319,26 @@ impl SmcData {
         }
     }
 
+    /// Numeric value as `f64`, `None` for flags, strings and raw bytes
+    ///
+    /// ```
+    /// # use macsmc::SmcData;
+    /// assert_eq!(SmcData::Float(1.5).as_f64(), Some(1.5));
+    /// assert_eq!(SmcData::Int(-3).as_f64(), Some(-3.0));
+    /// assert_eq!(SmcData::Uint(7).as_f64(), Some(7.0));
+    /// assert_eq!(SmcData::Flag(true).as_f64(), None);
+    /// assert_eq!(SmcData::String("x".to_string()).as_f64(), None);
+    /// assert_eq!(SmcData::Raw(vec![1]).as_f64(), None);
+    /// ```
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            SmcData::Float(f) => Some(*f as f64),
+            SmcData::Int(i) => Some(*i as f64),
+            SmcData::Uint(u) => Some(*u as f64),
+            SmcData::Flag(_) | SmcData::String(_) | SmcData::Raw(_) => None,
+        }
+    }
+
     /// Get raw string value
     pub fn as_string(&self) -> Result<String> {
         match self {
@@ -233,4 +349,122 @@ impl SmcData {
             }),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Decode an SMC `flt ` value from the first four bytes
+///
+/// Unlike the integer and fixed point types, which the SMC stores big-endian,
+/// `flt ` values come out little-endian. Both Intel and Apple Silicon Macs are
+/// little-endian, so decoding explicitly (rather than in native byte order)
+/// only matters for inspecting captured bytes on other machines, but keeps
+/// every `flt ` read in the crate going through the same byte order.
+fn decode_flt(data: &[u8]) -> Option<f32> {
+    data.get(..4)?.try_into().ok().map(f32::from_le_bytes)
+}
+
+/// Decode the limit of a structured `{lim`/`{ala` (limit/alarm) key
+///
+/// These types have no public layout, so this is conservative. Assumed layout:
+///
+/// | bytes | content                                 |
+/// |-------|-----------------------------------------|
+/// | 0..4  | limit in watts, little-endian `flt `    |
+/// | 4..   | unknown (flags, alarm state), ignored   |
+///
+/// Returns `None` (keeping the raw bytes) for fewer than four bytes or a
+/// limit that isn't finite and non-negative, as that means the assumption
+/// doesn't hold for the key.
+fn decode_limit(data: &[u8]) -> Option<f32> {
+    decode_flt(data).filter(|limit| limit.is_finite() && *limit >= 0.0)
+}
+
+/// Serializes the active value; raw bytes become a hex string
+#[cfg(feature = "serde")]
+impl serde::Serialize for SmcData {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            SmcData::Flag(b) => serializer.serialize_bool(*b),
+            SmcData::Float(v) => serializer.serialize_f32(*v),
+            SmcData::Int(i) => serializer.serialize_i64(*i),
+            SmcData::Uint(u) => serializer.serialize_u64(*u),
+            SmcData::String(s) => serializer.serialize_str(s),
+            SmcData::Raw(bytes) => {
+                let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+                serializer.serialize_str(&hex)
+            }
+        }
+    }
+}
+
+impl fmt::Display for SmcData {
+    /// Plain value without a unit; raw bytes are shown as hex
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SmcData::Flag(b) => write!(f, "{}", b),
+            SmcData::Float(v) => write!(f, "{}", v),
+            SmcData::Int(i) => write!(f, "{}", i),
+            SmcData::Uint(u) => write!(f, "{}", u),
+            SmcData::String(s) => write!(f, "{}", s),
+            SmcData::Raw(bytes) => {
+                for (i, byte) in bytes.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{:02x}", byte)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_keys_decode_to_watts() {
+        // 28.0 W as little-endian flt, followed by bytes of unknown meaning
+        let bytes = [0x00, 0x00, 0xe0, 0x41, 0x01, 0x00, 0x00, 0x00];
+        for data_type in &["{lim", "{ala"] {
+            match SmcData::parse(&bytes, data_type).unwrap() {
+                SmcData::Float(limit) => assert_eq!(limit, 28.0),
+                other => panic!("{} decoded to {:?}", data_type, other),
+            }
+        }
+    }
+
+    #[test]
+    fn implausible_limits_keep_raw_bytes() {
+        let short = [0x00, 0x00, 0xe0];
+        let negative = (-5.0_f32).to_le_bytes();
+        let nan = f32::NAN.to_le_bytes();
+        for bytes in &[&short[..], &negative[..], &nan[..]] {
+            match SmcData::parse(bytes, "{lim").unwrap() {
+                SmcData::Raw(raw) => assert_eq!(&raw[..], *bytes),
+                other => panic!("{:?} decoded to {:?}", bytes, other),
+            }
+        }
+    }
+
+    fn ch8(data: &[u8]) -> String {
+        match SmcData::parse(data, "ch8*").unwrap() {
+            SmcData::String(s) => s,
+            other => panic!("{:?} decoded to {:?}", data, other),
+        }
+    }
+
+    #[test]
+    fn ch8_strips_nul_padding() {
+        assert_eq!(ch8(b"Left\0\0\0"), "Left");
+        assert_eq!(ch8(b"Left\0garbage"), "Left");
+        assert_eq!(ch8(b"Right"), "Right");
+        assert_eq!(ch8(b"\0\0\0\0"), "");
+    }
+
+    #[test]
+    fn ch8_blanks_control_chars() {
+        assert_eq!(ch8(b"Le\x07ft\0\0"), "Le ft");
+        assert_eq!(ch8(b"Right\x01\x02"), "Right");
+    }
+}
@@ -0,0 +1,161 @@
+//! Structured dump of all SMC keys grouped by kind
+
+use crate::{data::SmcData, keys::KeyDataType};
+
+/// A decoded SMC key
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DumpEntry {
+    /// The SMC key
+    pub key: String,
+    /// Data type string
+    pub data_type: String,
+    /// Decoded value
+    pub value: SmcData,
+}
+
+/// Decoded SMC keys grouped by their inferred kind
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StructuredDump {
+    /// Temperature keys (`T...`)
+    pub temperatures: Vec<DumpEntry>,
+    /// Power keys (`P...`)
+    pub power: Vec<DumpEntry>,
+    /// Voltage keys (`V...`)
+    pub voltages: Vec<DumpEntry>,
+    /// Current keys (`I...`)
+    pub currents: Vec<DumpEntry>,
+    /// Fan speed keys (`F<n>Ac`, `F<n>Mx`, ...)
+    pub fans: Vec<DumpEntry>,
+    /// Boolean flags
+    pub flags: Vec<DumpEntry>,
+    /// String values
+    pub strings: Vec<DumpEntry>,
+    /// Everything else, including values that could not be decoded
+    pub unknown: Vec<DumpEntry>,
+}
+
+impl StructuredDump {
+    /// Add an entry to the group matching its value and key prefix
+    pub fn insert(&mut self, entry: DumpEntry) {
+        let group = match entry.value {
+            SmcData::Flag(_) => &mut self.flags,
+            SmcData::String(_) => &mut self.strings,
+            SmcData::Raw(_) => &mut self.unknown,
+            SmcData::Float(_) | SmcData::Int(_) | SmcData::Uint(_) => {
+                match infer_data_type(&entry.key) {
+                    Some(KeyDataType::Temperature) => &mut self.temperatures,
+                    Some(KeyDataType::Power) => &mut self.power,
+                    Some(KeyDataType::Voltage) => &mut self.voltages,
+                    Some(KeyDataType::Current) => &mut self.currents,
+                    Some(KeyDataType::FanSpeed) => &mut self.fans,
+                    _ => &mut self.unknown,
+                }
+            }
+        };
+        group.push(entry);
+    }
+
+    /// Total number of entries in all groups
+    pub fn len(&self) -> usize {
+        self.temperatures.len()
+            + self.power.len()
+            + self.voltages.len()
+            + self.currents.len()
+            + self.fans.len()
+            + self.flags.len()
+            + self.strings.len()
+            + self.unknown.len()
+    }
+
+    /// Whether the dump has no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Guess the kind of a numeric key from its prefix
+///
+/// This follows the common SMC naming scheme: `T` temperature, `P` power,
+/// `V` voltage, `I` current and `F<n>` plus a speed suffix for fans.
+pub fn infer_data_type(key: &str) -> Option<KeyDataType> {
+    let bytes = key.as_bytes();
+    if bytes.len() != 4 {
+        return None;
+    }
+
+    match bytes[0] {
+        b'T' => Some(KeyDataType::Temperature),
+        b'P' => Some(KeyDataType::Power),
+        b'V' => Some(KeyDataType::Voltage),
+        b'I' => Some(KeyDataType::Current),
+        b'F' if bytes[1].is_ascii_digit()
+            && matches!(&key[2..], "Ac" | "As" | "Mn" | "Mx" | "Tg" | "Sf") =>
+        {
+            Some(KeyDataType::FanSpeed)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key: &str, data_type: &str, value: SmcData) -> DumpEntry {
+        DumpEntry {
+            key: key.to_string(),
+            data_type: data_type.to_string(),
+            value,
+        }
+    }
+
+    fn keys(group: &[DumpEntry]) -> Vec<&str> {
+        group.iter().map(|entry| entry.key.as_str()).collect()
+    }
+
+    #[test]
+    fn mixed_keys_land_in_their_groups() {
+        let mut dump = StructuredDump::default();
+        for entry in vec![
+            entry("TC0P", "flt ", SmcData::Float(45.0)),
+            entry("PSTR", "flt ", SmcData::Float(12.5)),
+            entry("VD0R", "flt ", SmcData::Float(20.1)),
+            entry("IC0R", "flt ", SmcData::Float(1.2)),
+            entry("F0Ac", "fpe2", SmcData::Float(1200.0)),
+            entry("F1Mx", "flt ", SmcData::Float(6000.0)),
+            entry("BATP", "flag", SmcData::Flag(true)),
+            entry("RPlt", "ch8*", SmcData::String("j314".to_string())),
+            entry("#KEY", "ui32", SmcData::Uint(1500)),
+            entry("FNum", "ui8 ", SmcData::Uint(2)),
+            entry("F0ID", "{fds", SmcData::Raw(vec![0, 1, 2])),
+            entry("Tp01", "xyz ", SmcData::Raw(vec![0xff])),
+        ] {
+            dump.insert(entry);
+        }
+
+        assert_eq!(keys(&dump.temperatures), ["TC0P"]);
+        assert_eq!(keys(&dump.power), ["PSTR"]);
+        assert_eq!(keys(&dump.voltages), ["VD0R"]);
+        assert_eq!(keys(&dump.currents), ["IC0R"]);
+        assert_eq!(keys(&dump.fans), ["F0Ac", "F1Mx"]);
+        assert_eq!(keys(&dump.flags), ["BATP"]);
+        assert_eq!(keys(&dump.strings), ["RPlt"]);
+        assert_eq!(keys(&dump.unknown), ["#KEY", "FNum", "F0ID", "Tp01"]);
+        assert_eq!(dump.len(), 12);
+    }
+
+    #[test]
+    fn infers_kind_from_key_prefix() {
+        assert_eq!(infer_data_type("TC0P"), Some(KeyDataType::Temperature));
+        assert_eq!(infer_data_type("PCPT"), Some(KeyDataType::Power));
+        assert_eq!(infer_data_type("VG0C"), Some(KeyDataType::Voltage));
+        assert_eq!(infer_data_type("IG0R"), Some(KeyDataType::Current));
+        assert_eq!(infer_data_type("F2Tg"), Some(KeyDataType::FanSpeed));
+        assert_eq!(infer_data_type("F0Md"), None);
+        assert_eq!(infer_data_type("FNum"), None);
+        assert_eq!(infer_data_type("BATP"), None);
+        assert_eq!(infer_data_type("TC0"), None);
+    }
+}
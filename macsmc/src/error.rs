@@ -10,33 +10,219 @@ pub type Result<T> = std::result::Result<T, SmcError>;
 pub enum SmcError {
     /// SMC is not available on this system
     NotAvailable,
+    /// SMC exists but could not be opened, with the IOKit return code
+    Busy(i32),
     /// Insufficient privileges to access SMC
     InsufficientPrivileges,
+    /// SMC device went away, e.g. while the system is asleep
+    NoDevice,
+    /// SMC did not respond in time
+    NotResponding,
     /// SMC returned an error code
     SmcError(i32),
     /// Invalid SMC key format
     InvalidKey(String),
+    /// SMC key does not exist on this machine
+    KeyNotFound(String),
     /// Data parsing error
     DataError { key: String, data_type: String },
     /// Unknown data type
     UnknownDataType(String),
+    /// Key data of this SMC type could not be decoded and was left as raw bytes
+    UndecodedType {
+        /// Key that was read
+        key: String,
+        /// SMC data type of the key (e.g. `"{fds"`)
+        data_type: String,
+    },
+    /// No built-in composite sensor has this name
+    UnknownSensor(String),
+    /// Fan index is not below the number of fans the SMC reports
+    NoSuchFan {
+        /// Requested fan index
+        fan: u8,
+        /// Number of fans the SMC reports (`FNum`)
+        count: u8,
+    },
+    /// Value to write lies outside the range the key accepts
+    OutOfRange {
+        /// Key that was to be written
+        key: String,
+        /// Rejected value
+        value: f32,
+        /// Lowest accepted value
+        min: f32,
+        /// Highest accepted value
+        max: f32,
+    },
+}
+
+// IOKit return codes that indicate a temporary condition
+const IO_RETURN_BUSY: i32 = 0xe00002d5_u32 as i32;
+const IO_RETURN_TIMEOUT: i32 = 0xe00002d6_u32 as i32;
+const IO_RETURN_NOT_READY: i32 = 0xe00002d8_u32 as i32;
+const IO_RETURN_ABORTED: i32 = 0xe00002eb_u32 as i32;
+
+// IOKit return codes with their own error variant
+const IO_RETURN_NO_DEVICE: i32 = 0xe00002c0_u32 as i32;
+const IO_RETURN_NOT_PRIVILEGED: i32 = 0xe00002c1_u32 as i32;
+const IO_RETURN_NOT_PERMITTED: i32 = 0xe00002e2_u32 as i32;
+const IO_RETURN_NOT_RESPONDING: i32 = 0xe00002ed_u32 as i32;
+
+impl SmcError {
+    /// Classify a failed IOKit return code
+    ///
+    /// Well-known codes map to their own variants, anything else is kept as
+    /// `SmcError(code)`.
+    pub fn from_io_return(code: i32) -> Self {
+        match code {
+            IO_RETURN_NO_DEVICE => SmcError::NoDevice,
+            IO_RETURN_NOT_RESPONDING => SmcError::NotResponding,
+            IO_RETURN_NOT_PRIVILEGED | IO_RETURN_NOT_PERMITTED => SmcError::InsufficientPrivileges,
+            code => SmcError::SmcError(code),
+        }
+    }
+
+    /// Name the key a `DataError` belongs to
+    ///
+    /// Conversions such as `SmcData::as_temperature` don't know which key
+    /// the data came from and report it as "unknown". Other errors are
+    /// returned unchanged.
+    pub fn for_key(self, key: &str) -> Self {
+        match self {
+            SmcError::DataError { data_type, .. } => SmcError::DataError {
+                key: key.to_string(),
+                data_type,
+            },
+            e => e,
+        }
+    }
+
+    /// Whether retrying the failed operation may succeed
+    ///
+    /// A `Busy` SMC that failed to open is transient, as are a device that
+    /// went away during sleep, an SMC that is not responding and `SmcError`
+    /// codes for busy, timed out, not ready or aborted IOKit calls.
+    /// A missing SMC, missing privileges, bad keys and data errors will fail
+    /// the same way again.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            SmcError::SmcError(code) => matches!(
+                *code,
+                IO_RETURN_BUSY | IO_RETURN_TIMEOUT | IO_RETURN_NOT_READY | IO_RETURN_ABORTED
+            ),
+            SmcError::Busy(_) | SmcError::NoDevice | SmcError::NotResponding => true,
+            SmcError::NotAvailable
+            | SmcError::InsufficientPrivileges
+            | SmcError::InvalidKey(_)
+            | SmcError::KeyNotFound(_)
+            | SmcError::DataError { .. }
+            | SmcError::UnknownDataType(_)
+            | SmcError::UndecodedType { .. }
+            | SmcError::UnknownSensor(_)
+            | SmcError::NoSuchFan { .. }
+            | SmcError::OutOfRange { .. } => false,
+        }
+    }
 }
 
 impl fmt::Display for SmcError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             SmcError::NotAvailable => write!(f, "SMC is not available"),
+            SmcError::Busy(code) => write!(f, "SMC could not be opened: {:#x}", code),
             SmcError::InsufficientPrivileges => write!(f, "Insufficient privileges to access SMC"),
+            SmcError::NoDevice => write!(f, "SMC device not present (is the system asleep?)"),
+            SmcError::NotResponding => write!(f, "SMC is not responding"),
             SmcError::SmcError(code) => write!(f, "SMC error: {:#x}", code),
             SmcError::InvalidKey(key) => write!(f, "Invalid SMC key: {}", key),
+            SmcError::KeyNotFound(key) => write!(f, "SMC key not found: {}", key),
             SmcError::DataError { key, data_type } => {
                 write!(f, "Data error for key {}: type {}", key, data_type)
             }
             SmcError::UnknownDataType(data_type) => {
                 write!(f, "Unknown data type: {}", data_type)
             }
+            SmcError::UndecodedType { key, data_type } => {
+                write!(f, "Cannot decode key {} of type {:?}", key, data_type)
+            }
+            SmcError::UnknownSensor(name) => write!(f, "Unknown sensor: {}", name),
+            SmcError::NoSuchFan { fan, count } => {
+                write!(f, "No fan {}: this machine has {} fan(s)", fan, count)
+            }
+            SmcError::OutOfRange { key, value, min, max } => {
+                write!(f, "Value {} for key {} is outside {}..={}", value, key, min, max)
+            }
         }
     }
 }
 
-impl StdError for SmcError {}
\ No newline at end of file
+impl StdError for SmcError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transient_variants() {
+        for code in [IO_RETURN_BUSY, IO_RETURN_TIMEOUT, IO_RETURN_NOT_READY, IO_RETURN_ABORTED] {
+            assert!(SmcError::SmcError(code).is_transient(), "{:#x}", code);
+        }
+        assert!(SmcError::Busy(IO_RETURN_BUSY).is_transient());
+        assert!(SmcError::NoDevice.is_transient());
+        assert!(SmcError::NotResponding.is_transient());
+    }
+
+    #[test]
+    fn permanent_variants() {
+        let permanent = [
+            SmcError::SmcError(0xe00002bc_u32 as i32),
+            SmcError::NotAvailable,
+            SmcError::InsufficientPrivileges,
+            SmcError::InvalidKey("TC0".to_string()),
+            SmcError::KeyNotFound("TC0P".to_string()),
+            SmcError::DataError {
+                key: "TC0P".to_string(),
+                data_type: "flt ".to_string(),
+            },
+            SmcError::UnknownDataType("xyz ".to_string()),
+            SmcError::UndecodedType {
+                key: "TC0P".to_string(),
+                data_type: "xyz ".to_string(),
+            },
+            SmcError::UnknownSensor("Fan Power".to_string()),
+            SmcError::NoSuchFan { fan: 2, count: 2 },
+            SmcError::OutOfRange {
+                key: "F0Tg".to_string(),
+                value: 9000.0,
+                min: 1000.0,
+                max: 6000.0,
+            },
+        ];
+        for error in &permanent {
+            assert!(!error.is_transient(), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn io_return_codes_map_to_variants() {
+        assert!(matches!(SmcError::from_io_return(IO_RETURN_NO_DEVICE), SmcError::NoDevice));
+        assert!(matches!(
+            SmcError::from_io_return(IO_RETURN_NOT_RESPONDING),
+            SmcError::NotResponding
+        ));
+        assert!(matches!(
+            SmcError::from_io_return(IO_RETURN_NOT_PRIVILEGED),
+            SmcError::InsufficientPrivileges
+        ));
+        assert!(matches!(
+            SmcError::from_io_return(IO_RETURN_NOT_PERMITTED),
+            SmcError::InsufficientPrivileges
+        ));
+        assert!(matches!(
+            SmcError::from_io_return(IO_RETURN_TIMEOUT),
+            SmcError::SmcError(code) if code == IO_RETURN_TIMEOUT
+        ));
+        assert!(matches!(SmcError::from_io_return(-1), SmcError::SmcError(-1)));
+    }
+}
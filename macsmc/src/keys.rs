@@ -1,4 +1,7 @@
-//! SMC key definitions for Apple Silicon M2 and other macOS systems
+//! SMC key definitions for Apple Silicon and other macOS systems
+
+use crate::platform::Platform;
+use std::{fmt, ops::Deref, str::FromStr, sync::RwLock};
 
 /// SMC key structure
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -11,8 +14,39 @@ pub struct SmcKey {
     pub category: KeyCategory,
     /// Expected data type
     pub data_type: KeyDataType,
+    /// Factor converting the raw value into the unit of `data_type`
+    ///
+    /// `1.0` leaves the value to the default conversion of its SMC type
+    /// (e.g. integer voltages are read as millivolts).
+    pub scale: Scale,
+}
+
+/// Factor converting a raw value into the unit of a key's data type
+///
+/// Compared by bit pattern, so keys holding one can be `Eq`.
+#[derive(Debug, Clone, Copy)]
+pub struct Scale(pub f32);
+
+impl Scale {
+    /// No scaling
+    pub const ONE: Scale = Scale(1.0);
+}
+
+impl Deref for Scale {
+    type Target = f32;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl PartialEq for Scale {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
 }
 
+impl Eq for Scale {}
+
 /// SMC key categories
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum KeyCategory {
@@ -53,6 +87,59 @@ pub enum KeyDataType {
     String,
 }
 
+/// CPU core cluster kinds on Apple Silicon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CoreKind {
+    /// Efficiency (E) core
+    Efficiency,
+    /// Performance (P) core
+    Performance,
+}
+
+impl fmt::Display for CoreKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoreKind::Efficiency => write!(f, "efficiency"),
+            CoreKind::Performance => write!(f, "performance"),
+        }
+    }
+}
+
+impl FromStr for KeyCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cpu" => Ok(KeyCategory::Cpu),
+            "gpu" => Ok(KeyCategory::Gpu),
+            "system" => Ok(KeyCategory::System),
+            "sensor" => Ok(KeyCategory::Sensor),
+            "fan" => Ok(KeyCategory::Fan),
+            "battery" => Ok(KeyCategory::Battery),
+            "power" => Ok(KeyCategory::Power),
+            _ => Err(format!("unknown key category: {}", s)),
+        }
+    }
+}
+
+impl FromStr for KeyDataType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "temperature" => Ok(KeyDataType::Temperature),
+            "voltage" => Ok(KeyDataType::Voltage),
+            "current" => Ok(KeyDataType::Current),
+            "power" => Ok(KeyDataType::Power),
+            "fan_speed" => Ok(KeyDataType::FanSpeed),
+            "flag" => Ok(KeyDataType::Flag),
+            "numeric" => Ok(KeyDataType::Numeric),
+            "string" => Ok(KeyDataType::String),
+            _ => Err(format!("unknown key data type: {}", s)),
+        }
+    }
+}
+
 impl SmcKey {
     /// Create a new SMC key
     pub const fn new(
@@ -66,8 +153,34 @@ impl SmcKey {
             name,
             category,
             data_type,
+            scale: Scale::ONE,
+        }
+    }
+
+    /// Use a fixed factor to convert the raw value, e.g. `0.1` for deci-volts
+    pub const fn with_scale(self, scale: f32) -> Self {
+        Self {
+            scale: Scale(scale),
+            ..self
         }
     }
+
+    /// Core kind of a CPU core key, derived from its name
+    pub fn core_kind(&self) -> Option<CoreKind> {
+        if self.name.contains("Efficiency") {
+            Some(CoreKind::Efficiency)
+        } else if self.name.contains("Performance") {
+            Some(CoreKind::Performance)
+        } else {
+            None
+        }
+    }
+
+    /// Core index of a CPU core key, taken from the trailing number of its name
+    pub fn core_index(&self) -> Option<u8> {
+        self.core_kind()?;
+        self.name.rsplit(' ').next()?.parse().ok()
+    }
 }
 
 /// Convert 4-character string to u32 key
@@ -85,66 +198,342 @@ pub fn u32_to_key(key: u32) -> String {
     String::from_utf8_lossy(&bytes).to_string()
 }
 
+/// Key of a per-fan value, e.g. `fan_key(1, "Ac")` is `F1Ac`
+pub fn fan_key(fan: u8, suffix: &str) -> String {
+    format!("F{}{}", fan, suffix)
+}
+
+/// Keys of the values of a single fan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FanKeys {
+    /// Current speed
+    pub actual: String,
+    /// Current speed on firmware that reports it under `F<n>As` instead
+    pub actual_alternate: String,
+    /// Minimum speed
+    pub min: String,
+    /// Maximum speed
+    pub max: String,
+    /// Target speed
+    pub target: String,
+    /// Safe speed
+    pub safe: String,
+    /// Control mode (0 = automatic, 1 = forced)
+    pub mode: String,
+}
+
+impl FanKeys {
+    /// Keys of a fan
+    ///
+    /// Intel Macs report `fpe2` values and Apple Silicon Macs `flt ` values,
+    /// both under the same suffixes.
+    pub fn new(fan: u8) -> Self {
+        Self {
+            actual: fan_key(fan, "Ac"),
+            actual_alternate: fan_key(fan, "As"),
+            min: fan_key(fan, "Mn"),
+            max: fan_key(fan, "Mx"),
+            target: fan_key(fan, "Tg"),
+            safe: fan_key(fan, "Sf"),
+            mode: fan_key(fan, "Md"),
+        }
+    }
+
+    /// Key to read the minimum speed from when `min` is missing or reads 0
+    ///
+    /// Some Apple Silicon fan machines leave `F<n>Mn` at 0 and only report
+    /// the lowest speed the fan runs at as its safe speed.
+    pub fn min_fallback(&self, platform: Platform) -> Option<&str> {
+        if platform.is_intel() {
+            None
+        } else {
+            Some(&self.safe)
+        }
+    }
+}
+
+// M1 CPU Temperature Keys
+/// M1 CPU efficiency core 1 temperature
+pub const M1_CPU_EFFICIENCY_CORE_1: SmcKey = SmcKey::new("Tp09", "CPU Efficiency Core 1", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M1 CPU efficiency core 2 temperature
+pub const M1_CPU_EFFICIENCY_CORE_2: SmcKey = SmcKey::new("Tp0T", "CPU Efficiency Core 2", KeyCategory::Cpu, KeyDataType::Temperature);
+
+/// M1 CPU performance core 1 temperature
+pub const M1_CPU_PERFORMANCE_CORE_1: SmcKey = SmcKey::new("Tp01", "CPU Performance Core 1", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M1 CPU performance core 2 temperature
+pub const M1_CPU_PERFORMANCE_CORE_2: SmcKey = SmcKey::new("Tp05", "CPU Performance Core 2", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M1 CPU performance core 3 temperature
+pub const M1_CPU_PERFORMANCE_CORE_3: SmcKey = SmcKey::new("Tp0D", "CPU Performance Core 3", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M1 CPU performance core 4 temperature
+pub const M1_CPU_PERFORMANCE_CORE_4: SmcKey = SmcKey::new("Tp0H", "CPU Performance Core 4", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M1 CPU performance core 5 temperature
+pub const M1_CPU_PERFORMANCE_CORE_5: SmcKey = SmcKey::new("Tp0L", "CPU Performance Core 5", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M1 CPU performance core 6 temperature
+pub const M1_CPU_PERFORMANCE_CORE_6: SmcKey = SmcKey::new("Tp0P", "CPU Performance Core 6", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M1 CPU performance core 7 temperature
+pub const M1_CPU_PERFORMANCE_CORE_7: SmcKey = SmcKey::new("Tp0X", "CPU Performance Core 7", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M1 CPU performance core 8 temperature
+pub const M1_CPU_PERFORMANCE_CORE_8: SmcKey = SmcKey::new("Tp0b", "CPU Performance Core 8", KeyCategory::Cpu, KeyDataType::Temperature);
+
+// M1 GPU Temperature Keys
+/// M1 GPU 1 temperature
+pub const M1_GPU_1: SmcKey = SmcKey::new("Tg05", "GPU 1", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M1 GPU 2 temperature
+pub const M1_GPU_2: SmcKey = SmcKey::new("Tg0D", "GPU 2", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M1 GPU 3 temperature
+pub const M1_GPU_3: SmcKey = SmcKey::new("Tg0L", "GPU 3", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M1 GPU 4 temperature
+pub const M1_GPU_4: SmcKey = SmcKey::new("Tg0T", "GPU 4", KeyCategory::Gpu, KeyDataType::Temperature);
+
 // M2 CPU Temperature Keys
-pub const M2_CPU_EFFICIENCY_CORE_1: SmcKey = SmcKey::new("Te05", "CPU Efficiency Core", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M2 CPU efficiency core 1 temperature
+pub const M2_CPU_EFFICIENCY_CORE_1: SmcKey = SmcKey::new("Te05", "CPU Efficiency Core 1", KeyCategory::Cpu, KeyDataType::Temperature);
 
+/// M2 CPU performance core 1 temperature
 pub const M2_CPU_PERFORMANCE_CORE_1: SmcKey = SmcKey::new("Tp01", "CPU Performance Core 1", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M2 CPU performance core 2 temperature
 pub const M2_CPU_PERFORMANCE_CORE_2: SmcKey = SmcKey::new("Tp05", "CPU Performance Core 2", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M2 CPU performance core 3 temperature
 pub const M2_CPU_PERFORMANCE_CORE_3: SmcKey = SmcKey::new("Tp09", "CPU Performance Core 3", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M2 CPU performance core 4 temperature
 pub const M2_CPU_PERFORMANCE_CORE_4: SmcKey = SmcKey::new("Tp0D", "CPU Performance Core 4", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M2 CPU performance core 5 temperature
 pub const M2_CPU_PERFORMANCE_CORE_5: SmcKey = SmcKey::new("Tp0X", "CPU Performance Core 5", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M2 CPU performance core 6 temperature
 pub const M2_CPU_PERFORMANCE_CORE_6: SmcKey = SmcKey::new("Tp0b", "CPU Performance Core 6", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M2 CPU performance core 7 temperature
 pub const M2_CPU_PERFORMANCE_CORE_7: SmcKey = SmcKey::new("Tp0f", "CPU Performance Core 7", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M2 CPU performance core 8 temperature
 pub const M2_CPU_PERFORMANCE_CORE_8: SmcKey = SmcKey::new("Tp0j", "CPU Performance Core 8", KeyCategory::Cpu, KeyDataType::Temperature);
 
 // M2 GPU Temperature Keys
+/// M2 GPU 1 temperature
 pub const M2_GPU_1: SmcKey = SmcKey::new("Tg0f", "GPU 1", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M2 GPU 2 temperature
 pub const M2_GPU_2: SmcKey = SmcKey::new("Tg0j", "GPU 2", KeyCategory::Gpu, KeyDataType::Temperature);
 
+// M3 CPU Temperature Keys
+/// M3 CPU efficiency core 1 temperature
+pub const M3_CPU_EFFICIENCY_CORE_1: SmcKey = SmcKey::new("Te05", "CPU Efficiency Core 1", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M3 CPU efficiency core 2 temperature
+pub const M3_CPU_EFFICIENCY_CORE_2: SmcKey = SmcKey::new("Te0L", "CPU Efficiency Core 2", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M3 CPU efficiency core 3 temperature
+pub const M3_CPU_EFFICIENCY_CORE_3: SmcKey = SmcKey::new("Te0P", "CPU Efficiency Core 3", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M3 CPU efficiency core 4 temperature
+pub const M3_CPU_EFFICIENCY_CORE_4: SmcKey = SmcKey::new("Te0S", "CPU Efficiency Core 4", KeyCategory::Cpu, KeyDataType::Temperature);
+
+/// M3 CPU performance core 1 temperature
+pub const M3_CPU_PERFORMANCE_CORE_1: SmcKey = SmcKey::new("Tf04", "CPU Performance Core 1", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M3 CPU performance core 2 temperature
+pub const M3_CPU_PERFORMANCE_CORE_2: SmcKey = SmcKey::new("Tf09", "CPU Performance Core 2", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M3 CPU performance core 3 temperature
+pub const M3_CPU_PERFORMANCE_CORE_3: SmcKey = SmcKey::new("Tf0A", "CPU Performance Core 3", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M3 CPU performance core 4 temperature
+pub const M3_CPU_PERFORMANCE_CORE_4: SmcKey = SmcKey::new("Tf0B", "CPU Performance Core 4", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M3 CPU performance core 5 temperature
+pub const M3_CPU_PERFORMANCE_CORE_5: SmcKey = SmcKey::new("Tf0D", "CPU Performance Core 5", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M3 CPU performance core 6 temperature
+pub const M3_CPU_PERFORMANCE_CORE_6: SmcKey = SmcKey::new("Tf0E", "CPU Performance Core 6", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M3 CPU performance core 7 temperature
+pub const M3_CPU_PERFORMANCE_CORE_7: SmcKey = SmcKey::new("Tf44", "CPU Performance Core 7", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M3 CPU performance core 8 temperature
+pub const M3_CPU_PERFORMANCE_CORE_8: SmcKey = SmcKey::new("Tf49", "CPU Performance Core 8", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M3 CPU performance core 9 temperature
+pub const M3_CPU_PERFORMANCE_CORE_9: SmcKey = SmcKey::new("Tf4A", "CPU Performance Core 9", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M3 CPU performance core 10 temperature
+pub const M3_CPU_PERFORMANCE_CORE_10: SmcKey = SmcKey::new("Tf4B", "CPU Performance Core 10", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M3 CPU performance core 11 temperature
+pub const M3_CPU_PERFORMANCE_CORE_11: SmcKey = SmcKey::new("Tf4D", "CPU Performance Core 11", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M3 CPU performance core 12 temperature
+pub const M3_CPU_PERFORMANCE_CORE_12: SmcKey = SmcKey::new("Tf4E", "CPU Performance Core 12", KeyCategory::Cpu, KeyDataType::Temperature);
+
+// M3 GPU Temperature Keys
+/// M3 GPU 1 temperature
+pub const M3_GPU_1: SmcKey = SmcKey::new("Tf14", "GPU 1", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M3 GPU 2 temperature
+pub const M3_GPU_2: SmcKey = SmcKey::new("Tf18", "GPU 2", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M3 GPU 3 temperature
+pub const M3_GPU_3: SmcKey = SmcKey::new("Tf19", "GPU 3", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M3 GPU 4 temperature
+pub const M3_GPU_4: SmcKey = SmcKey::new("Tf1A", "GPU 4", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M3 GPU 5 temperature
+pub const M3_GPU_5: SmcKey = SmcKey::new("Tf24", "GPU 5", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M3 GPU 6 temperature
+pub const M3_GPU_6: SmcKey = SmcKey::new("Tf28", "GPU 6", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M3 GPU 7 temperature
+pub const M3_GPU_7: SmcKey = SmcKey::new("Tf29", "GPU 7", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M3 GPU 8 temperature
+pub const M3_GPU_8: SmcKey = SmcKey::new("Tf2A", "GPU 8", KeyCategory::Gpu, KeyDataType::Temperature);
+
+// M4 CPU Temperature Keys
+/// M4 CPU efficiency core 1 temperature
+pub const M4_CPU_EFFICIENCY_CORE_1: SmcKey = SmcKey::new("Te05", "CPU Efficiency Core 1", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M4 CPU efficiency core 2 temperature
+pub const M4_CPU_EFFICIENCY_CORE_2: SmcKey = SmcKey::new("Te0S", "CPU Efficiency Core 2", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M4 CPU efficiency core 3 temperature
+pub const M4_CPU_EFFICIENCY_CORE_3: SmcKey = SmcKey::new("Te09", "CPU Efficiency Core 3", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M4 CPU efficiency core 4 temperature
+pub const M4_CPU_EFFICIENCY_CORE_4: SmcKey = SmcKey::new("Te0H", "CPU Efficiency Core 4", KeyCategory::Cpu, KeyDataType::Temperature);
+
+/// M4 CPU performance core 1 temperature
+pub const M4_CPU_PERFORMANCE_CORE_1: SmcKey = SmcKey::new("Tp01", "CPU Performance Core 1", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M4 CPU performance core 2 temperature
+pub const M4_CPU_PERFORMANCE_CORE_2: SmcKey = SmcKey::new("Tp05", "CPU Performance Core 2", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M4 CPU performance core 3 temperature
+pub const M4_CPU_PERFORMANCE_CORE_3: SmcKey = SmcKey::new("Tp09", "CPU Performance Core 3", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M4 CPU performance core 4 temperature
+pub const M4_CPU_PERFORMANCE_CORE_4: SmcKey = SmcKey::new("Tp0D", "CPU Performance Core 4", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M4 CPU performance core 5 temperature
+pub const M4_CPU_PERFORMANCE_CORE_5: SmcKey = SmcKey::new("Tp0V", "CPU Performance Core 5", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M4 CPU performance core 6 temperature
+pub const M4_CPU_PERFORMANCE_CORE_6: SmcKey = SmcKey::new("Tp0Y", "CPU Performance Core 6", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M4 CPU performance core 7 temperature
+pub const M4_CPU_PERFORMANCE_CORE_7: SmcKey = SmcKey::new("Tp0b", "CPU Performance Core 7", KeyCategory::Cpu, KeyDataType::Temperature);
+/// M4 CPU performance core 8 temperature
+pub const M4_CPU_PERFORMANCE_CORE_8: SmcKey = SmcKey::new("Tp0e", "CPU Performance Core 8", KeyCategory::Cpu, KeyDataType::Temperature);
+
+// M4 GPU Temperature Keys
+/// M4 GPU 1 temperature
+pub const M4_GPU_1: SmcKey = SmcKey::new("Tg0G", "GPU 1", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M4 GPU 2 temperature
+pub const M4_GPU_2: SmcKey = SmcKey::new("Tg0H", "GPU 2", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M4 GPU 3 temperature
+pub const M4_GPU_3: SmcKey = SmcKey::new("Tg1U", "GPU 3", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M4 GPU 4 temperature
+pub const M4_GPU_4: SmcKey = SmcKey::new("Tg1k", "GPU 4", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M4 GPU 5 temperature
+pub const M4_GPU_5: SmcKey = SmcKey::new("Tg0K", "GPU 5", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M4 GPU 6 temperature
+pub const M4_GPU_6: SmcKey = SmcKey::new("Tg0L", "GPU 6", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M4 GPU 7 temperature
+pub const M4_GPU_7: SmcKey = SmcKey::new("Tg0d", "GPU 7", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M4 GPU 8 temperature
+pub const M4_GPU_8: SmcKey = SmcKey::new("Tg0e", "GPU 8", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M4 GPU 9 temperature
+pub const M4_GPU_9: SmcKey = SmcKey::new("Tg0j", "GPU 9", KeyCategory::Gpu, KeyDataType::Temperature);
+/// M4 GPU 10 temperature
+pub const M4_GPU_10: SmcKey = SmcKey::new("Tg0k", "GPU 10", KeyCategory::Gpu, KeyDataType::Temperature);
+
 // Universal CPU Temperature Keys
+/// CPU diode temperature
 pub const CPU_DIODE: SmcKey = SmcKey::new("TC0D", "CPU Diode", KeyCategory::Cpu, KeyDataType::Temperature);
+/// CPU diode filtered temperature
 pub const CPU_DIODE_FILTERED: SmcKey = SmcKey::new("TC0F", "CPU Diode Filtered", KeyCategory::Cpu, KeyDataType::Temperature);
+/// CPU proximity temperature
 pub const CPU_PROXIMITY: SmcKey = SmcKey::new("TC0P", "CPU Proximity", KeyCategory::Cpu, KeyDataType::Temperature);
+/// CPU package temperature
 pub const CPU_PACKAGE: SmcKey = SmcKey::new("TCAD", "CPU Package", KeyCategory::Cpu, KeyDataType::Temperature);
 
 // Universal GPU Temperature Keys
+/// GPU Intel graphics temperature
 pub const GPU_INTEL_GRAPHICS: SmcKey = SmcKey::new("TCGC", "GPU Intel Graphics", KeyCategory::Gpu, KeyDataType::Temperature);
+/// GPU proximity temperature
 pub const GPU_PROXIMITY: SmcKey = SmcKey::new("TG0P", "GPU Proximity", KeyCategory::Gpu, KeyDataType::Temperature);
+/// GPU AMD Radeon temperature
 pub const GPU_AMD_RADEON: SmcKey = SmcKey::new("TGDD", "GPU AMD Radeon", KeyCategory::Gpu, KeyDataType::Temperature);
 
 // System Temperature Keys
+/// Airflow left temperature
 pub const AIRFLOW_LEFT: SmcKey = SmcKey::new("TaLP", "Airflow Left", KeyCategory::Sensor, KeyDataType::Temperature);
+/// Airflow right temperature
 pub const AIRFLOW_RIGHT: SmcKey = SmcKey::new("TaRF", "Airflow Right", KeyCategory::Sensor, KeyDataType::Temperature);
+/// NAND storage temperature
 pub const NAND_STORAGE: SmcKey = SmcKey::new("TH0x", "NAND Storage", KeyCategory::System, KeyDataType::Temperature);
+/// NAND storage A temperature
+pub const NAND_STORAGE_A: SmcKey = SmcKey::new("TH0a", "NAND Storage A", KeyCategory::System, KeyDataType::Temperature);
+/// NAND storage B temperature
+pub const NAND_STORAGE_B: SmcKey = SmcKey::new("TH0b", "NAND Storage B", KeyCategory::System, KeyDataType::Temperature);
+/// Battery 1 temperature
 pub const BATTERY_1: SmcKey = SmcKey::new("TB1T", "Battery 1", KeyCategory::System, KeyDataType::Temperature);
+/// Battery 2 temperature
 pub const BATTERY_2: SmcKey = SmcKey::new("TB2T", "Battery 2", KeyCategory::System, KeyDataType::Temperature);
+/// Airport temperature
 pub const AIRPORT: SmcKey = SmcKey::new("TW0P", "Airport", KeyCategory::System, KeyDataType::Temperature);
+/// Mainboard temperature
 pub const MAINBOARD: SmcKey = SmcKey::new("Tm0P", "Mainboard", KeyCategory::System, KeyDataType::Temperature);
+/// Thunderbolt left temperature
 pub const THUNDERBOLT_LEFT: SmcKey = SmcKey::new("TTLD", "Thunderbolt Left", KeyCategory::System, KeyDataType::Temperature);
+/// Thunderbolt right temperature
 pub const THUNDERBOLT_RIGHT: SmcKey = SmcKey::new("TTRD", "Thunderbolt Right", KeyCategory::System, KeyDataType::Temperature);
+/// Ambient temperature
+pub const AMBIENT: SmcKey = SmcKey::new("TA0P", "Ambient", KeyCategory::System, KeyDataType::Temperature);
 
 // Power Keys
+/// CPU package power
 pub const POWER_CPU_PACKAGE: SmcKey = SmcKey::new("PCPC", "CPU Package Power", KeyCategory::Power, KeyDataType::Power);
+/// CPU total power
 pub const POWER_CPU_TOTAL: SmcKey = SmcKey::new("PCPT", "CPU Total Power", KeyCategory::Power, KeyDataType::Power);
+/// GPU 1 power
 pub const POWER_GPU_1: SmcKey = SmcKey::new("PG0R", "GPU 1 Power", KeyCategory::Power, KeyDataType::Power);
+/// GPU 2 power
 pub const POWER_GPU_2: SmcKey = SmcKey::new("PG1R", "GPU 2 Power", KeyCategory::Power, KeyDataType::Power);
+/// System total power
 pub const POWER_SYSTEM_TOTAL: SmcKey = SmcKey::new("PSTR", "System Total Power", KeyCategory::Power, KeyDataType::Power);
+/// DC input power
 pub const POWER_DC_IN: SmcKey = SmcKey::new("PDTR", "DC Input Power", KeyCategory::Power, KeyDataType::Power);
 
+// Meta Keys
+/// Number of keys the SMC knows
+pub const KEY_COUNT: SmcKey = SmcKey::new("#KEY", "Key Count", KeyCategory::System, KeyDataType::Numeric);
+
 // Fan Keys
+/// Number of fans
 pub const FAN_COUNT: SmcKey = SmcKey::new("FNum", "Fan Count", KeyCategory::Fan, KeyDataType::Numeric);
+/// Fan test mode flag
+///
+/// Apple Silicon only accepts forced fan modes while this is set
+pub const FAN_TEST_MODE: SmcKey = SmcKey::new("Ftst", "Fan Test Mode", KeyCategory::Fan, KeyDataType::Flag);
 
 // Battery Keys
+/// Whether the machine is running on battery
 pub const BATTERY_POWERED: SmcKey = SmcKey::new("BATP", "Battery Powered", KeyCategory::Battery, KeyDataType::Flag);
 
+// Power Adapter Keys
+/// Rated wattage of the connected power adapter
+pub const ADAPTER_WATTAGE: SmcKey = SmcKey::new("AC-W", "Adapter Wattage", KeyCategory::Power, KeyDataType::Power);
+/// DC input voltage
+pub const ADAPTER_VOLTAGE: SmcKey = SmcKey::new("VD0R", "DC Input Voltage", KeyCategory::Power, KeyDataType::Voltage);
+/// DC input current
+pub const ADAPTER_CURRENT: SmcKey = SmcKey::new("ID0R", "DC Input Current", KeyCategory::Power, KeyDataType::Current);
+
 // Voltage Keys
+/// CPU core voltage
 pub const VOLTAGE_CPU_CORE: SmcKey = SmcKey::new("VCAC", "CPU Core Voltage", KeyCategory::Cpu, KeyDataType::Voltage);
+/// GPU voltage
 pub const VOLTAGE_GPU: SmcKey = SmcKey::new("VG0C", "GPU Voltage", KeyCategory::Gpu, KeyDataType::Voltage);
 
 // Current Keys
+/// CPU current
 pub const CURRENT_CPU: SmcKey = SmcKey::new("IC0R", "CPU Current", KeyCategory::Cpu, KeyDataType::Current);
+/// GPU current
 pub const CURRENT_GPU: SmcKey = SmcKey::new("IG0R", "GPU Current", KeyCategory::Gpu, KeyDataType::Current);
 
+/// Get all M1 CPU core temperature keys
+pub fn m1_cpu_temperature_keys() -> Vec<&'static SmcKey> {
+    vec![
+        &M1_CPU_EFFICIENCY_CORE_1,
+        &M1_CPU_EFFICIENCY_CORE_2,
+        &M1_CPU_PERFORMANCE_CORE_1,
+        &M1_CPU_PERFORMANCE_CORE_2,
+        &M1_CPU_PERFORMANCE_CORE_3,
+        &M1_CPU_PERFORMANCE_CORE_4,
+        &M1_CPU_PERFORMANCE_CORE_5,
+        &M1_CPU_PERFORMANCE_CORE_6,
+        &M1_CPU_PERFORMANCE_CORE_7,
+        &M1_CPU_PERFORMANCE_CORE_8,
+    ]
+}
+
+/// Get all M1 GPU temperature keys
+pub fn m1_gpu_temperature_keys() -> Vec<&'static SmcKey> {
+    vec![
+        &M1_GPU_1,
+        &M1_GPU_2,
+        &M1_GPU_3,
+        &M1_GPU_4,
+    ]
+}
+
 /// Get all M2 CPU core temperature keys
 pub fn m2_cpu_temperature_keys() -> Vec<&'static SmcKey> {
     vec![
@@ -165,18 +554,386 @@ pub fn m2_gpu_temperature_keys() -> Vec<&'static SmcKey> {
     vec![&M2_GPU_1]
 }
 
-/// Get system temperature keys
-pub fn system_temperature_keys() -> Vec<&'static SmcKey> {
+/// Get all M3 CPU core temperature keys
+pub fn m3_cpu_temperature_keys() -> Vec<&'static SmcKey> {
+    vec![
+        &M3_CPU_EFFICIENCY_CORE_1,
+        &M3_CPU_EFFICIENCY_CORE_2,
+        &M3_CPU_EFFICIENCY_CORE_3,
+        &M3_CPU_EFFICIENCY_CORE_4,
+        &M3_CPU_PERFORMANCE_CORE_1,
+        &M3_CPU_PERFORMANCE_CORE_2,
+        &M3_CPU_PERFORMANCE_CORE_3,
+        &M3_CPU_PERFORMANCE_CORE_4,
+        &M3_CPU_PERFORMANCE_CORE_5,
+        &M3_CPU_PERFORMANCE_CORE_6,
+        &M3_CPU_PERFORMANCE_CORE_7,
+        &M3_CPU_PERFORMANCE_CORE_8,
+        &M3_CPU_PERFORMANCE_CORE_9,
+        &M3_CPU_PERFORMANCE_CORE_10,
+        &M3_CPU_PERFORMANCE_CORE_11,
+        &M3_CPU_PERFORMANCE_CORE_12,
+    ]
+}
+
+/// Get all M3 GPU temperature keys
+pub fn m3_gpu_temperature_keys() -> Vec<&'static SmcKey> {
+    vec![
+        &M3_GPU_1,
+        &M3_GPU_2,
+        &M3_GPU_3,
+        &M3_GPU_4,
+        &M3_GPU_5,
+        &M3_GPU_6,
+        &M3_GPU_7,
+        &M3_GPU_8,
+    ]
+}
+
+/// Get all M4 CPU core temperature keys
+pub fn m4_cpu_temperature_keys() -> Vec<&'static SmcKey> {
+    vec![
+        &M4_CPU_EFFICIENCY_CORE_1,
+        &M4_CPU_EFFICIENCY_CORE_2,
+        &M4_CPU_EFFICIENCY_CORE_3,
+        &M4_CPU_EFFICIENCY_CORE_4,
+        &M4_CPU_PERFORMANCE_CORE_1,
+        &M4_CPU_PERFORMANCE_CORE_2,
+        &M4_CPU_PERFORMANCE_CORE_3,
+        &M4_CPU_PERFORMANCE_CORE_4,
+        &M4_CPU_PERFORMANCE_CORE_5,
+        &M4_CPU_PERFORMANCE_CORE_6,
+        &M4_CPU_PERFORMANCE_CORE_7,
+        &M4_CPU_PERFORMANCE_CORE_8,
+    ]
+}
+
+/// Get all M4 GPU temperature keys
+pub fn m4_gpu_temperature_keys() -> Vec<&'static SmcKey> {
+    vec![
+        &M4_GPU_1,
+        &M4_GPU_2,
+        &M4_GPU_3,
+        &M4_GPU_4,
+        &M4_GPU_5,
+        &M4_GPU_6,
+        &M4_GPU_7,
+        &M4_GPU_8,
+        &M4_GPU_9,
+        &M4_GPU_10,
+    ]
+}
+
+/// Get Intel CPU temperature keys
+pub fn intel_cpu_temperature_keys() -> Vec<&'static SmcKey> {
+    vec![
+        &CPU_PROXIMITY,
+        &CPU_DIODE_FILTERED,
+        &CPU_DIODE,
+        &CPU_PACKAGE,
+    ]
+}
+
+/// Keys for a single CPU temperature, in order of preference
+///
+/// The filtered diode (`TC0F`) comes before the raw one (`TC0D`) since it
+/// doesn't jump around as much, with the proximity sensor as a last resort.
+pub fn cpu_die_temperature_keys() -> Vec<&'static SmcKey> {
+    vec![
+        &CPU_DIODE_FILTERED,
+        &CPU_DIODE,
+        &CPU_PROXIMITY,
+    ]
+}
+
+/// Keys for the discrete GPU temperature on Intel Macs, in order of preference
+///
+/// The die sensor (`TGDD`) comes before the proximity one (`TG0P`). The
+/// integrated GPU has its own key, `GPU_INTEL_GRAPHICS`.
+pub fn discrete_gpu_temperature_keys() -> Vec<&'static SmcKey> {
     vec![
+        &GPU_AMD_RADEON,
+        &GPU_PROXIMITY,
+    ]
+}
+
+/// Get Intel GPU temperature keys
+pub fn intel_gpu_temperature_keys() -> Vec<&'static SmcKey> {
+    vec![
+        &GPU_PROXIMITY,
+        &GPU_AMD_RADEON,
+        &GPU_INTEL_GRAPHICS,
+    ]
+}
+
+/// Get the CPU temperature keys for a platform, including registered user keys
+///
+/// `Platform::Unknown` only gets the user keys registered for every platform.
+pub fn cpu_temperature_keys(platform: Platform) -> Vec<&'static SmcKey> {
+    let mut keys = match platform.generation() {
+        // Every chip names its sensors differently, so there are no universal built-in keys
+        None if platform == Platform::Unknown => Vec::new(),
+        None => intel_cpu_temperature_keys(),
+        Some(1) => m1_cpu_temperature_keys(),
+        Some(3) => m3_cpu_temperature_keys(),
+        Some(4) => m4_cpu_temperature_keys(),
+        Some(_) => m2_cpu_temperature_keys(),
+    };
+    keys.extend(user_keys(&[KeyCategory::Cpu], KeyDataType::Temperature, Some(platform)));
+    keys
+}
+
+/// Get the GPU temperature keys for a platform, including registered user keys
+///
+/// `Platform::Unknown` only gets the user keys registered for every platform.
+pub fn gpu_temperature_keys(platform: Platform) -> Vec<&'static SmcKey> {
+    let mut keys = match platform.generation() {
+        // Every chip names its sensors differently, so there are no universal built-in keys
+        None if platform == Platform::Unknown => Vec::new(),
+        None => intel_gpu_temperature_keys(),
+        Some(1) => m1_gpu_temperature_keys(),
+        Some(3) => m3_gpu_temperature_keys(),
+        Some(4) => m4_gpu_temperature_keys(),
+        Some(_) => m2_gpu_temperature_keys(),
+    };
+    keys.extend(user_keys(&[KeyCategory::Gpu], KeyDataType::Temperature, Some(platform)));
+    keys
+}
+
+/// Get system temperature keys, including registered user keys
+pub fn system_temperature_keys() -> Vec<&'static SmcKey> {
+    let mut keys = vec![
         &NAND_STORAGE,
+        &NAND_STORAGE_A,
+        &NAND_STORAGE_B,
         &AIRPORT,
+        &AMBIENT,
+    ];
+    keys.extend(user_keys(
+        &[KeyCategory::System, KeyCategory::Sensor],
+        KeyDataType::Temperature,
+        None,
+    ));
+    keys
+}
+
+/// Get the NAND (SSD) temperature keys
+///
+/// Apple Silicon Macs report their SSD on `TH0a`/`TH0b`, older ones on `TH0x`.
+pub fn ssd_temperature_keys() -> Vec<&'static SmcKey> {
+    vec![
+        &NAND_STORAGE_A,
+        &NAND_STORAGE_B,
+        &NAND_STORAGE,
     ]
 }
 
-/// Get power monitoring keys
+/// Get power monitoring keys, including registered user keys
 pub fn power_keys() -> Vec<&'static SmcKey> {
-    vec![
+    let mut keys = vec![
         &POWER_SYSTEM_TOTAL,
         &POWER_DC_IN,
+    ];
+    keys.extend(user_keys(&[KeyCategory::Power], KeyDataType::Power, None));
+    keys
+}
+
+/// Get voltage rail keys
+pub fn voltage_keys() -> Vec<&'static SmcKey> {
+    let mut keys = vec![
+        &VOLTAGE_CPU_CORE,
+        &VOLTAGE_GPU,
+    ];
+    keys.extend(user_keys(RAIL_CATEGORIES, KeyDataType::Voltage, None));
+    keys
+}
+
+/// Get current rail keys
+pub fn current_keys() -> Vec<&'static SmcKey> {
+    let mut keys = vec![
+        &CURRENT_CPU,
+        &CURRENT_GPU,
+    ];
+    keys.extend(user_keys(RAIL_CATEGORIES, KeyDataType::Current, None));
+    keys
+}
+
+/// A logical sensor computed from the readings of two SMC keys
+#[derive(Debug, Clone, Copy)]
+pub struct CompositeSensor {
+    /// Human-readable name, also used to look the sensor up
+    pub name: &'static str,
+    /// First input key
+    pub a: &'static SmcKey,
+    /// Second input key
+    pub b: &'static SmcKey,
+    /// Combines the readings of `a` and `b`, each in the unit of its data type
+    pub combine: fn(f64, f64) -> f64,
+}
+
+// Power of rails without a direct power key, as current × voltage
+/// CPU power from the CPU current and core voltage
+pub const POWER_CPU_COMPUTED: CompositeSensor = CompositeSensor {
+    name: "CPU Power (computed)",
+    a: &CURRENT_CPU,
+    b: &VOLTAGE_CPU_CORE,
+    combine: |current, voltage| current * voltage,
+};
+/// GPU power from the GPU current and voltage
+pub const POWER_GPU_COMPUTED: CompositeSensor = CompositeSensor {
+    name: "GPU Power (computed)",
+    a: &CURRENT_GPU,
+    b: &VOLTAGE_GPU,
+    combine: |current, voltage| current * voltage,
+};
+
+/// Get the built-in composite sensors
+pub fn composite_sensors() -> Vec<&'static CompositeSensor> {
+    vec![
+        &POWER_CPU_COMPUTED,
+        &POWER_GPU_COMPUTED,
     ]
-}
\ No newline at end of file
+}
+
+// Categories of user keys included in the voltage and current lists
+const RAIL_CATEGORIES: &[KeyCategory] = &[
+    KeyCategory::Cpu,
+    KeyCategory::Gpu,
+    KeyCategory::System,
+    KeyCategory::Power,
+];
+
+/// A key registered at runtime, optionally limited to some platforms
+struct UserKey {
+    key: &'static SmcKey,
+    platforms: Option<Vec<Platform>>,
+}
+
+static USER_KEYS: RwLock<Vec<UserKey>> = RwLock::new(Vec::new());
+
+/// Add a key to the built-in key lists for the rest of the process
+///
+/// The key is included in the list matching its category and data type,
+/// for all platforms or only the given ones. Registering the same 4-character
+/// key again replaces the earlier definition. Registered keys are never freed.
+pub fn register_key(key: SmcKey, platforms: Option<Vec<Platform>>) {
+    let key = Box::leak(Box::new(key));
+    if let Ok(mut user_keys) = USER_KEYS.write() {
+        user_keys.retain(|user| user.key.key != key.key);
+        user_keys.push(UserKey { key, platforms });
+    }
+}
+
+fn user_keys(
+    categories: &[KeyCategory],
+    data_type: KeyDataType,
+    platform: Option<Platform>,
+) -> Vec<&'static SmcKey> {
+    let user_keys = match USER_KEYS.read() {
+        Ok(user_keys) => user_keys,
+        Err(_) => return Vec::new(),
+    };
+
+    user_keys
+        .iter()
+        .filter(|user| categories.contains(&user.key.category) && user.key.data_type == data_type)
+        .filter(|user| match (&user.platforms, platform) {
+            (Some(platforms), Some(platform)) => platforms.contains(&platform),
+            _ => true,
+        })
+        .map(|user| user.key)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_platform_only_gets_universal_user_keys() {
+        register_key(
+            SmcKey::new("Tu0a", "Universal CPU", KeyCategory::Cpu, KeyDataType::Temperature),
+            None,
+        );
+        register_key(
+            SmcKey::new("Tu0b", "M1 only CPU", KeyCategory::Cpu, KeyDataType::Temperature),
+            Some(vec![Platform::M1]),
+        );
+
+        let keys: Vec<&str> = cpu_temperature_keys(Platform::Unknown)
+            .iter()
+            .map(|key| key.key)
+            .collect();
+        assert!(keys.contains(&"Tu0a"));
+        assert!(!keys.contains(&"Tu0b"));
+        assert!(intel_cpu_temperature_keys().iter().all(|key| !keys.contains(&key.key)));
+        assert!(gpu_temperature_keys(Platform::Unknown)
+            .iter()
+            .all(|key| !intel_gpu_temperature_keys().contains(key)));
+    }
+
+    #[test]
+    fn registering_a_key_again_replaces_it() {
+        register_key(
+            SmcKey::new("Tu1a", "First", KeyCategory::System, KeyDataType::Temperature),
+            None,
+        );
+        register_key(
+            SmcKey::new("Tu1a", "Second", KeyCategory::System, KeyDataType::Temperature),
+            None,
+        );
+
+        let names: Vec<&str> = system_temperature_keys()
+            .iter()
+            .filter(|key| key.key == "Tu1a")
+            .map(|key| key.name)
+            .collect();
+        assert_eq!(names, ["Second"]);
+    }
+
+    fn key_names(keys: &[&SmcKey]) -> Vec<&'static str> {
+        keys.iter().map(|key| key.key).collect()
+    }
+
+    #[test]
+    fn built_in_cpu_lists_are_non_empty_without_duplicates() {
+        let lists = [
+            ("M1", m1_cpu_temperature_keys()),
+            ("M2", m2_cpu_temperature_keys()),
+            ("M3", m3_cpu_temperature_keys()),
+            ("M4", m4_cpu_temperature_keys()),
+            ("Intel", intel_cpu_temperature_keys()),
+        ];
+        for (name, keys) in &lists {
+            assert!(!keys.is_empty(), "{} has no CPU keys", name);
+            let mut unique = key_names(keys);
+            unique.sort_unstable();
+            unique.dedup();
+            assert_eq!(unique.len(), keys.len(), "{} has duplicate CPU keys", name);
+        }
+    }
+
+    #[test]
+    fn cpu_keys_are_picked_by_generation() {
+        let cases = [
+            (Platform::Intel, intel_cpu_temperature_keys()),
+            (Platform::M1, m1_cpu_temperature_keys()),
+            (Platform::M1Max, m1_cpu_temperature_keys()),
+            (Platform::M2, m2_cpu_temperature_keys()),
+            (Platform::M2Pro, m2_cpu_temperature_keys()),
+            (Platform::M3, m3_cpu_temperature_keys()),
+            (Platform::M4, m4_cpu_temperature_keys()),
+            (Platform::M4Pro, m4_cpu_temperature_keys()),
+        ];
+        for (platform, built_in) in &cases {
+            // Registered user keys (see the other tests) come after the built-in ones
+            let keys = cpu_temperature_keys(*platform);
+            assert_eq!(
+                key_names(&keys[..built_in.len()]),
+                key_names(built_in),
+                "{:?}",
+                platform
+            );
+        }
+    }
+}
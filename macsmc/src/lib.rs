@@ -10,16 +10,29 @@ compile_error!("This crate only works on macOS");
 
 pub mod client;
 pub mod data;
+pub mod dump;
 pub mod error;
 pub mod keys;
+pub mod platform;
+pub mod smoothed;
+pub mod source;
 pub mod types;
 
 pub use client::SmcClient;
 pub use data::SmcData;
+pub use dump::StructuredDump;
 pub use error::{Result, SmcError};
+pub use platform::Platform;
+pub use smoothed::SmoothedSmc;
+pub use source::SensorSource;
 pub use types::*;
 
 /// Create a new SMC client connection
 pub fn connect() -> Result<SmcClient> {
     SmcClient::new()
+}
+
+/// Create a new SMC client connection that detects the platform only when needed
+pub fn connect_raw() -> Result<SmcClient> {
+    SmcClient::new_raw()
 }
\ No newline at end of file
@@ -0,0 +1,247 @@
+//! Platform (chip) detection for macOS systems
+
+use std::{ffi::CString, fmt, os::raw::c_void, ptr, str::FromStr};
+
+/// Mac hardware platform, identified by its chip
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Platform {
+    /// Intel based Mac
+    Intel,
+    /// Apple M1
+    M1,
+    /// Apple M1 Pro
+    M1Pro,
+    /// Apple M1 Max
+    M1Max,
+    /// Apple M1 Ultra
+    M1Ultra,
+    /// Apple M2
+    M2,
+    /// Apple M2 Pro
+    M2Pro,
+    /// Apple M2 Max
+    M2Max,
+    /// Apple M2 Ultra
+    M2Ultra,
+    /// Apple M3
+    M3,
+    /// Apple M3 Pro
+    M3Pro,
+    /// Apple M3 Max
+    M3Max,
+    /// Apple M3 Ultra
+    M3Ultra,
+    /// Apple M4
+    M4,
+    /// Apple M4 Pro
+    M4Pro,
+    /// Apple M4 Max
+    M4Max,
+    /// Chip could not be identified; only universal keys are used
+    #[default]
+    Unknown,
+}
+
+impl Platform {
+    /// All known platforms (everything but `Unknown`)
+    pub fn all() -> &'static [Platform] {
+        &[
+            Platform::Intel,
+            Platform::M1,
+            Platform::M1Pro,
+            Platform::M1Max,
+            Platform::M1Ultra,
+            Platform::M2,
+            Platform::M2Pro,
+            Platform::M2Max,
+            Platform::M2Ultra,
+            Platform::M3,
+            Platform::M3Pro,
+            Platform::M3Max,
+            Platform::M3Ultra,
+            Platform::M4,
+            Platform::M4Pro,
+            Platform::M4Max,
+        ]
+    }
+
+    /// All Apple Silicon platforms
+    pub fn apple_silicon() -> &'static [Platform] {
+        &Self::all()[1..]
+    }
+
+    /// Whether this is an Intel based Mac
+    pub fn is_intel(self) -> bool {
+        matches!(self, Platform::Intel)
+    }
+
+    /// Apple Silicon generation (1 for M1, 2 for M2, ...), `None` on Intel and `Unknown`
+    pub fn generation(self) -> Option<u8> {
+        match self {
+            Platform::Intel | Platform::Unknown => None,
+            Platform::M1 | Platform::M1Pro | Platform::M1Max | Platform::M1Ultra => Some(1),
+            Platform::M2 | Platform::M2Pro | Platform::M2Max | Platform::M2Ultra => Some(2),
+            Platform::M3 | Platform::M3Pro | Platform::M3Max | Platform::M3Ultra => Some(3),
+            Platform::M4 | Platform::M4Pro | Platform::M4Max => Some(4),
+        }
+    }
+
+    /// Parse a CPU brand string such as "Apple M2 Pro"
+    ///
+    /// The chip and its variant are matched as whole words, so extra words
+    /// ("Apple M2 chip") are ignored and "Apple M20" is not taken for an M2.
+    pub fn from_brand_string(brand: &str) -> Option<Self> {
+        if brand.contains("Intel") {
+            return Some(Platform::Intel);
+        }
+
+        let brand = brand.to_lowercase();
+        let words: Vec<&str> = brand
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        let (position, variants): (usize, &[Platform]) =
+            words.iter().enumerate().find_map(|(i, word)| {
+                let variants: &[Platform] = match *word {
+                    "m1" => &[Platform::M1, Platform::M1Pro, Platform::M1Max, Platform::M1Ultra],
+                    "m2" => &[Platform::M2, Platform::M2Pro, Platform::M2Max, Platform::M2Ultra],
+                    "m3" => &[Platform::M3, Platform::M3Pro, Platform::M3Max, Platform::M3Ultra],
+                    "m4" => &[Platform::M4, Platform::M4Pro, Platform::M4Max],
+                    _ => return None,
+                };
+                Some((i, variants))
+            })?;
+
+        let suffix = words[position + 1..].iter().find_map(|word| match *word {
+            "pro" => Some(1),
+            "max" => Some(2),
+            "ultra" => Some(3),
+            _ => None,
+        });
+        variants.get(suffix.unwrap_or(0)).copied()
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Platform::Intel => "Intel",
+            Platform::M1 => "M1",
+            Platform::M1Pro => "M1 Pro",
+            Platform::M1Max => "M1 Max",
+            Platform::M1Ultra => "M1 Ultra",
+            Platform::M2 => "M2",
+            Platform::M2Pro => "M2 Pro",
+            Platform::M2Max => "M2 Max",
+            Platform::M2Ultra => "M2 Ultra",
+            Platform::M3 => "M3",
+            Platform::M3Pro => "M3 Pro",
+            Platform::M3Max => "M3 Max",
+            Platform::M3Ultra => "M3 Ultra",
+            Platform::M4 => "M4",
+            Platform::M4Pro => "M4 Pro",
+            Platform::M4Max => "M4 Max",
+            Platform::Unknown => "Unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    /// Parse a platform name such as "M2 Pro" or "Intel", ignoring case and spaces
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalize = |name: &str| name.replace(' ', "").to_lowercase();
+        let wanted = normalize(s);
+        Platform::all()
+            .iter()
+            .copied()
+            .find(|platform| normalize(&platform.to_string()) == wanted)
+            .ok_or_else(|| format!("unknown platform: {}", s))
+    }
+}
+
+/// Detect the platform of the running machine from its CPU brand string
+pub fn detect_platform() -> Option<Platform> {
+    sysctl_string("machdep.cpu.brand_string").and_then(|brand| Platform::from_brand_string(&brand))
+}
+
+/// macOS product version of the running machine (e.g. "14.5")
+pub fn os_version() -> Option<String> {
+    sysctl_string("kern.osproductversion")
+}
+
+/// Model identifier of the running machine (e.g. "Mac14,2")
+pub fn model() -> Option<String> {
+    sysctl_string("hw.model")
+}
+
+/// Identity of the running machine, for tagging metrics per machine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineInfo {
+    /// Model identifier (e.g. "Mac14,2")
+    pub model: String,
+    /// Serial number, `None` if it could not be read
+    pub serial: Option<String>,
+}
+
+fn sysctl_string(name: &str) -> Option<String> {
+    let name = CString::new(name).ok()?;
+    let mut size: libc::size_t = 0;
+
+    let result =
+        unsafe { libc::sysctlbyname(name.as_ptr(), ptr::null_mut(), &mut size, ptr::null_mut(), 0) };
+    if result != 0 || size == 0 {
+        return None;
+    }
+
+    let mut buf = vec![0_u8; size];
+    let result = unsafe {
+        libc::sysctlbyname(
+            name.as_ptr(),
+            buf.as_mut_ptr() as *mut c_void,
+            &mut size,
+            ptr::null_mut(),
+            0,
+        )
+    };
+    if result != 0 {
+        return None;
+    }
+
+    buf.truncate(size);
+    while buf.last() == Some(&0) {
+        buf.pop();
+    }
+    String::from_utf8(buf).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brand_strings_with_extra_words() {
+        assert_eq!(Platform::from_brand_string("Apple M2 chip"), Some(Platform::M2));
+        assert_eq!(Platform::from_brand_string("Apple M2 Pro"), Some(Platform::M2Pro));
+        assert_eq!(Platform::from_brand_string("Apple M3 Max chip"), Some(Platform::M3Max));
+        assert_eq!(Platform::from_brand_string("Apple M1 Ultra"), Some(Platform::M1Ultra));
+    }
+
+    #[test]
+    fn unknown_chips_are_not_matched_by_prefix() {
+        assert_eq!(Platform::from_brand_string("Apple M20"), None);
+        assert_eq!(Platform::from_brand_string("Apple M4 Ultra"), None);
+        assert_eq!(Platform::from_brand_string(""), None);
+    }
+
+    #[test]
+    fn intel_brand_string() {
+        assert_eq!(
+            Platform::from_brand_string("Intel(R) Core(TM) i9-9880H CPU @ 2.30GHz"),
+            Some(Platform::Intel)
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Exponentially smoothed readings for stable dashboards
+
+use crate::{
+    client::SmcClient,
+    error::*,
+    keys::{SmcKey, POWER_SYSTEM_TOTAL},
+    types::{Celsius, Watt},
+};
+use std::collections::HashMap;
+
+/// SMC client wrapper that smooths readings with an exponentially weighted moving average
+///
+/// Each reading updates the smoother of its key (or aggregate) and returns
+/// the smoothed value. The first reading is returned as is.
+#[derive(Debug)]
+pub struct SmoothedSmc {
+    client: SmcClient,
+    ewma: Ewma,
+}
+
+impl SmoothedSmc {
+    /// Wrap a client, weighting each new reading by `alpha`
+    ///
+    /// `alpha` is clamped to `0.01..=1.0`; 1.0 disables smoothing, smaller
+    /// values smooth more.
+    pub fn new(client: SmcClient, alpha: f32) -> Self {
+        Self {
+            client,
+            ewma: Ewma::new(alpha),
+        }
+    }
+
+    /// The wrapped client, for unsmoothed reads
+    pub fn client(&mut self) -> &mut SmcClient {
+        &mut self.client
+    }
+
+    /// Forget all smoothing state
+    pub fn reset(&mut self) {
+        self.ewma.values.clear();
+    }
+
+    /// Read a known key and smooth its numeric value
+    pub fn read_smoothed(&mut self, key: &SmcKey) -> Result<f32> {
+        let reading = self.client.read_typed(key)?;
+        let value = reading.value_f64().ok_or_else(|| SmcError::DataError {
+            key: key.key.to_string(),
+            data_type: "numeric".to_string(),
+        })?;
+        Ok(self.ewma.smooth(key.key, value as f32))
+    }
+
+    /// Smoothed mean of the CPU temperature sensors, `None` if none respond
+    pub fn cpu_temperature_smoothed(&mut self) -> Option<Celsius> {
+        let keys = self.client.cpu_temperature_keys();
+        self.mean_temperature("cpu_temperature", &keys)
+    }
+
+    /// Smoothed mean of the GPU temperature sensors, `None` if none respond
+    pub fn gpu_temperature_smoothed(&mut self) -> Option<Celsius> {
+        let keys = self.client.gpu_temperature_keys();
+        self.mean_temperature("gpu_temperature", &keys)
+    }
+
+    /// Smoothed total system power
+    pub fn system_power_smoothed(&mut self) -> Result<Watt> {
+        self.read_smoothed(&POWER_SYSTEM_TOTAL).map(Watt)
+    }
+
+    fn mean_temperature(&mut self, name: &str, keys: &[&'static SmcKey]) -> Option<Celsius> {
+        let temps: Vec<f32> = keys
+            .iter()
+            .filter_map(|key| crate::source::read_temperature(&mut self.client, key).ok())
+            .filter(|temp| temp.is_plausible())
+            .map(|temp| *temp)
+            .collect();
+        if temps.is_empty() {
+            return None;
+        }
+
+        let mean = temps.iter().sum::<f32>() / temps.len() as f32;
+        Some(Celsius(self.ewma.smooth(name, mean)))
+    }
+}
+
+/// Smoothing state of all keys and aggregates
+#[derive(Debug)]
+struct Ewma {
+    alpha: f32,
+    values: HashMap<String, f32>,
+}
+
+impl Ewma {
+    fn new(alpha: f32) -> Self {
+        let alpha = if alpha.is_nan() { 1.0 } else { alpha.clamp(0.01, 1.0) };
+        Self {
+            alpha,
+            values: HashMap::new(),
+        }
+    }
+
+    fn smooth(&mut self, name: &str, value: f32) -> f32 {
+        // A single bad reading would otherwise poison the average for good
+        if !value.is_finite() {
+            return value;
+        }
+
+        let alpha = self.alpha;
+        let smoothed = self
+            .values
+            .entry(name.to_string())
+            .and_modify(|smoothed| *smoothed += alpha * (value - *smoothed))
+            .or_insert(value);
+        *smoothed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_readings_stay_put() {
+        let mut ewma = Ewma::new(0.3);
+        for _ in 0..20 {
+            assert_eq!(ewma.smooth("TC0P", 42.0), 42.0);
+        }
+    }
+
+    #[test]
+    fn step_change_is_smoothed() {
+        let mut ewma = Ewma::new(0.5);
+        assert_eq!(ewma.smooth("TC0P", 40.0), 40.0);
+        assert_eq!(ewma.smooth("TC0P", 60.0), 50.0);
+        assert_eq!(ewma.smooth("TC0P", 60.0), 55.0);
+        assert_eq!(ewma.smooth("TC0P", 60.0), 57.5);
+
+        let converged = (0..50).map(|_| ewma.smooth("TC0P", 60.0)).last().unwrap();
+        assert!((converged - 60.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn keys_are_smoothed_separately() {
+        let mut ewma = Ewma::new(0.5);
+        ewma.smooth("TC0P", 40.0);
+        assert_eq!(ewma.smooth("TG0P", 70.0), 70.0);
+        assert_eq!(ewma.smooth("TC0P", 50.0), 45.0);
+    }
+
+    #[test]
+    fn non_finite_readings_are_not_averaged_in() {
+        let mut ewma = Ewma::new(0.5);
+        ewma.smooth("TC0P", 40.0);
+        assert!(ewma.smooth("TC0P", f32::NAN).is_nan());
+        assert_eq!(ewma.smooth("TC0P", 40.0), 40.0);
+    }
+
+    #[test]
+    fn alpha_is_clamped() {
+        assert_eq!(Ewma::new(f32::NAN).alpha, 1.0);
+        assert_eq!(Ewma::new(2.5).alpha, 1.0);
+        assert_eq!(Ewma::new(0.0).alpha, 0.01);
+        assert_eq!(Ewma::new(-1.0).alpha, 0.01);
+        assert_eq!(Ewma::new(0.25).alpha, 0.25);
+    }
+}
@@ -0,0 +1,482 @@
+//! Sensor reading on top of the `SensorSource` trait
+//!
+//! `SensorSource` is anything raw SMC keys can be read from: `SmcClient`
+//! implements it, and tests or captured data can provide their own. The free
+//! functions here (temperatures, fans, power, voltages, composite sensors)
+//! take a `&mut dyn SensorSource`, and the matching `SmcClient` methods
+//! delegate to them.
+
+use crate::{
+    client::SmcClient,
+    data::SmcData,
+    dump::infer_data_type,
+    error::*,
+    keys::{
+        composite_sensors, cpu_die_temperature_keys, cpu_temperature_keys,
+        discrete_gpu_temperature_keys, gpu_temperature_keys, power_keys, ssd_temperature_keys,
+        system_temperature_keys, voltage_keys, CompositeSensor, CoreKind, FanKeys, SmcKey,
+        ADAPTER_CURRENT, ADAPTER_VOLTAGE, ADAPTER_WATTAGE, AMBIENT, BATTERY_POWERED, FAN_COUNT,
+        GPU_INTEL_GRAPHICS,
+    },
+    platform::Platform,
+    types::{AdapterInfo, Celsius, FanSpeed, Rpm, SensorReading, Volt, Watt},
+};
+use std::collections::BTreeMap;
+
+/// Source of raw SMC key data
+///
+/// Lets derived-metric code read keys without depending on a live `SmcClient`.
+pub trait SensorSource {
+    /// Read a raw SMC key and return parsed data
+    fn read(&mut self, key: &str) -> Result<SmcData>;
+
+    /// Read a raw SMC key along with its SMC data type (e.g. `"ui8 "`)
+    ///
+    /// Sources that don't know the data type return an empty string.
+    fn read_with_type(&mut self, key: &str) -> Result<(String, SmcData)> {
+        self.read(key).map(|data| (String::new(), data))
+    }
+}
+
+impl SensorSource for SmcClient {
+    fn read(&mut self, key: &str) -> Result<SmcData> {
+        self.read_key(key)
+    }
+
+    fn read_with_type(&mut self, key: &str) -> Result<(String, SmcData)> {
+        self.read_with_info(key)
+            .map(|(info, data)| (info.data_type, data))
+    }
+}
+
+/// Read a key, returning `None` if it doesn't exist on this machine
+pub fn read_optional(source: &mut dyn SensorSource, key: &str) -> Result<Option<SmcData>> {
+    match source.read(key) {
+        Ok(data) => Ok(Some(data)),
+        Err(SmcError::KeyNotFound(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Read a temperature key, applying its scale
+///
+/// Keys reporting integer deci-degrees carry a scale of `0.1`, so `425`
+/// reads as 42.5 °C.
+pub fn read_temperature(source: &mut dyn SensorSource, key: &SmcKey) -> Result<Celsius> {
+    let data = source.read(key.key)?;
+    to_temperature(key, data)
+}
+
+fn to_temperature(key: &SmcKey, data: SmcData) -> Result<Celsius> {
+    data.scaled(*key.scale).as_temperature().map_err(|e| e.for_key(key.key))
+}
+
+/// Read the ambient (enclosure) temperature, `None` if the sensor is absent
+pub fn ambient_temperature(source: &mut dyn SensorSource) -> Result<Option<Celsius>> {
+    read_optional(source, AMBIENT.key)?
+        .map(|data| to_temperature(&AMBIENT, data))
+        .transpose()
+}
+
+/// Read a single CPU temperature from the first of `cpu_die_temperature_keys` that exists
+///
+/// `None` if the machine has none of them, as is the case on Apple Silicon,
+/// which only reports per-core temperatures.
+pub fn cpu_temperature(source: &mut dyn SensorSource) -> Result<Option<Celsius>> {
+    first_temperature(source, &cpu_die_temperature_keys())
+}
+
+/// Read the discrete GPU temperature from the first of `discrete_gpu_temperature_keys` that exists
+///
+/// `None` on machines without a discrete GPU, including Apple Silicon.
+pub fn gpu_temperature(source: &mut dyn SensorSource) -> Result<Option<Celsius>> {
+    first_temperature(source, &discrete_gpu_temperature_keys())
+}
+
+/// Read the integrated (Intel graphics) GPU temperature, `None` if the sensor is absent
+pub fn integrated_gpu_temperature(source: &mut dyn SensorSource) -> Result<Option<Celsius>> {
+    first_temperature(source, &[&GPU_INTEL_GRAPHICS])
+}
+
+fn first_temperature(
+    source: &mut dyn SensorSource,
+    keys: &[&'static SmcKey],
+) -> Result<Option<Celsius>> {
+    for key in keys {
+        if let Some(data) = read_optional(source, key.key)? {
+            return to_temperature(key, data).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// Read the hottest SSD (NAND) temperature, `None` if the machine has no such sensor
+pub fn ssd_temperature(source: &mut dyn SensorSource) -> Result<Option<Celsius>> {
+    let mut hottest: Option<Celsius> = None;
+    for key in ssd_temperature_keys() {
+        if let Some(data) = read_optional(source, key.key)? {
+            let temp = to_temperature(key, data)?;
+            if temp.is_plausible() && hottest.map_or(true, |hottest| temp > hottest) {
+                hottest = Some(temp);
+            }
+        }
+    }
+    Ok(hottest)
+}
+
+/// Read the connected power adapter, `None` when running on battery
+///
+/// Machines without a battery (no `BATP` key) are always on AC. Adapter keys
+/// the machine doesn't have are left as `None`.
+pub fn adapter_info(source: &mut dyn SensorSource) -> Result<Option<AdapterInfo>> {
+    let on_battery = read_optional(source, BATTERY_POWERED.key)?
+        .map(|data| data.as_bool().map_err(|e| e.for_key(BATTERY_POWERED.key)))
+        .transpose()?
+        .unwrap_or(false);
+    if on_battery {
+        return Ok(None);
+    }
+
+    Ok(Some(AdapterInfo {
+        wattage: source.read(ADAPTER_WATTAGE.key).and_then(|data| data.as_power()).ok(),
+        voltage: source.read(ADAPTER_VOLTAGE.key).and_then(|data| data.as_voltage()).ok(),
+        current: source.read(ADAPTER_CURRENT.key).and_then(|data| data.as_current()).ok(),
+    }))
+}
+
+/// Read the CPU core temperatures of a platform labeled with their core kind and index
+///
+/// Cores whose key cannot be read or reads an implausible value are skipped.
+pub fn labeled_cpu_core_temps(
+    source: &mut dyn SensorSource,
+    platform: Platform,
+) -> Result<Vec<(CoreKind, u8, Celsius)>> {
+    let mut temps = Vec::new();
+
+    for key in cpu_temperature_keys(platform) {
+        let (kind, index) = match (key.core_kind(), key.core_index()) {
+            (Some(kind), Some(index)) => (kind, index),
+            _ => continue,
+        };
+        let temp = read_temperature(source, key).ok().filter(|temp| temp.is_plausible());
+        if let Some(temp) = temp {
+            temps.push((kind, index, temp));
+        }
+    }
+
+    Ok(temps)
+}
+
+/// Read every known temperature sensor of a platform, keyed by sensor name
+///
+/// Covers CPU, GPU and system sensors. Sensors that cannot be read or read
+/// implausible values (see `Celsius::is_plausible`) are skipped.
+pub fn all_temperatures(
+    source: &mut dyn SensorSource,
+    platform: Platform,
+) -> Result<BTreeMap<String, Celsius>> {
+    let mut temps = BTreeMap::new();
+
+    let keys = cpu_temperature_keys(platform)
+        .into_iter()
+        .chain(gpu_temperature_keys(platform))
+        .chain(system_temperature_keys());
+    for key in keys {
+        if let Ok(temp) = read_temperature(source, key) {
+            if temp.is_plausible() {
+                temps.insert(key.name.to_string(), temp);
+            }
+        }
+    }
+
+    Ok(temps)
+}
+
+/// Read the power monitoring keys
+///
+/// With `skip_zero`, readings of exactly 0.0 W are treated as "not present"
+/// and omitted. Many Intel-era power keys read 0.0 on Apple Silicon, but an
+/// idle component can legitimately read 0.0 too, so this is opt-in.
+pub fn power_sensors(
+    source: &mut dyn SensorSource,
+    skip_zero: bool,
+) -> Vec<(&'static SmcKey, Watt)> {
+    let mut readings = Vec::new();
+
+    for key in power_keys() {
+        if let Ok(power) = source.read(key.key).and_then(|data| data.as_power()) {
+            if skip_zero && *power == 0.0 {
+                continue;
+            }
+            readings.push((key, power));
+        }
+    }
+
+    readings
+}
+
+/// Read the voltage rails of `voltage_keys`, skipping rails this machine doesn't have
+pub fn voltages(source: &mut dyn SensorSource) -> Vec<(&'static SmcKey, Volt)> {
+    let mut readings = Vec::new();
+
+    for key in voltage_keys() {
+        let voltage = source.read(key.key).and_then(|data| data.scaled(*key.scale).as_voltage());
+        if let Ok(voltage) = voltage {
+            readings.push((key, voltage));
+        }
+    }
+
+    readings
+}
+
+/// Read a key and convert it according to its data type and scale
+pub fn read_typed(source: &mut dyn SensorSource, key: &SmcKey) -> Result<SensorReading> {
+    let data = source.read(key.key)?.scaled(*key.scale);
+    SensorReading::from_data(data, &key.data_type).map_err(|e| e.for_key(key.key))
+}
+
+/// Read a composite sensor by name, ignoring case
+///
+/// Fails with `UnknownSensor` if there is no composite sensor of that name.
+pub fn read_composite(source: &mut dyn SensorSource, name: &str) -> Result<f64> {
+    let sensor = composite_sensors()
+        .into_iter()
+        .find(|sensor| sensor.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| SmcError::UnknownSensor(name.to_string()))?;
+    read_composite_sensor(source, sensor)
+}
+
+/// Read both keys of a composite sensor, typed by their data type and scale, and combine them
+///
+/// ```
+/// # use macsmc::{keys::POWER_CPU_COMPUTED, source::read_composite_sensor};
+/// # use macsmc::{Result, SensorSource, SmcData, SmcError};
+/// struct Fixed;
+///
+/// impl SensorSource for Fixed {
+///     fn read(&mut self, key: &str) -> Result<SmcData> {
+///         match key {
+///             "IC0R" => Ok(SmcData::Float(2.0)),
+///             "VCAC" => Ok(SmcData::Float(1.5)),
+///             _ => Err(SmcError::KeyNotFound(key.to_string())),
+///         }
+///     }
+/// }
+///
+/// // 2 A at 1.5 V
+/// assert_eq!(read_composite_sensor(&mut Fixed, &POWER_CPU_COMPUTED).unwrap(), 3.0);
+/// ```
+pub fn read_composite_sensor(
+    source: &mut dyn SensorSource,
+    sensor: &CompositeSensor,
+) -> Result<f64> {
+    let mut read = |key: &SmcKey| {
+        read_typed(source, key)?
+            .value_f64()
+            .ok_or_else(|| SmcError::DataError {
+                key: key.key.to_string(),
+                data_type: "numeric".to_string(),
+            })
+    };
+
+    let a = read(sensor.a)?;
+    let b = read(sensor.b)?;
+    Ok((sensor.combine)(a, b))
+}
+
+/// Read several raw keys, in the order given
+///
+/// Each key keeps its own result, so a missing key gives an `Err` entry
+/// instead of aborting the batch.
+pub fn read_many(source: &mut dyn SensorSource, keys: &[&str]) -> Vec<(String, Result<SmcData>)> {
+    keys.iter()
+        .map(|&key| (key.to_string(), source.read(key)))
+        .collect()
+}
+
+/// Read several keys, typing numeric values by their key prefix
+///
+/// Values whose kind cannot be inferred are returned as `SensorReading::Raw`.
+/// Each key keeps its own result, so one missing key doesn't fail the rest.
+pub fn read_many_typed(
+    source: &mut dyn SensorSource,
+    keys: &[&str],
+) -> BTreeMap<String, Result<SensorReading>> {
+    keys.iter()
+        .map(|&key| {
+            let reading = source.read(key).and_then(|data| {
+                let numeric =
+                    matches!(data, SmcData::Float(_) | SmcData::Int(_) | SmcData::Uint(_));
+                match infer_data_type(key) {
+                    Some(data_type) if numeric => {
+                        SensorReading::from_data(data, &data_type).map_err(|e| e.for_key(key))
+                    }
+                    _ => Ok(SensorReading::Raw(data)),
+                }
+            });
+            (key.to_string(), reading)
+        })
+        .collect()
+}
+
+/// Read the number of fans from `FNum`
+pub fn fan_count(source: &mut dyn SensorSource) -> Result<u8> {
+    let count = source
+        .read(FAN_COUNT.key)?
+        .as_u64()
+        .map_err(|e| e.for_key(FAN_COUNT.key))?;
+    Ok(count.min(u8::MAX as u64) as u8)
+}
+
+/// Read the current speed of a fan, falling back to `F<n>As` if `F<n>Ac` doesn't exist
+pub fn fan_actual_speed(source: &mut dyn SensorSource, fan: u8) -> Result<Rpm> {
+    let keys = FanKeys::new(fan);
+    let mut read_rpm = |key: &str| {
+        source
+            .read(key)
+            .and_then(|data| data.as_rpm().map_err(|e| e.for_key(key)))
+    };
+
+    match read_rpm(&keys.actual) {
+        Err(SmcError::KeyNotFound(_)) => read_rpm(&keys.actual_alternate),
+        result => result,
+    }
+}
+
+/// Read the speeds of a fan
+///
+/// The actual speed is required; minimum, maximum and target speeds that
+/// cannot be read are reported as 0 RPM. A `ui8` target is a PWM duty cycle
+/// and is reported as `target_percentage`.
+pub fn fan_speed(source: &mut dyn SensorSource, platform: Platform, fan: u8) -> Result<FanSpeed> {
+    let actual = fan_actual_speed(source, fan)?;
+
+    let keys = FanKeys::new(fan);
+    let mut read_rpm = |key: &str| {
+        source
+            .read(key)
+            .and_then(|data| data.as_rpm().map_err(|e| e.for_key(key)))
+    };
+    let mut min = read_rpm(&keys.min).unwrap_or(Rpm(0.0));
+    if min.0 <= 0.0 {
+        if let Some(fallback) = keys.min_fallback(platform) {
+            min = read_rpm(fallback).unwrap_or(min);
+        }
+    }
+    let max = read_rpm(&keys.max).unwrap_or(Rpm(0.0));
+
+    let (target, target_percentage) = match source.read_with_type(&keys.target) {
+        Ok((data_type, data)) if data_type == "ui8 " => match data.as_pwm_percentage() {
+            Ok(pwm) => (Rpm(min.0 + (max.0 - min.0).max(0.0) * pwm.0 / 100.0), Some(pwm)),
+            Err(_) => (Rpm(0.0), None),
+        },
+        Ok((_, data)) => (data.as_rpm().unwrap_or(Rpm(0.0)), None),
+        Err(_) => (Rpm(0.0), None),
+    };
+
+    Ok(FanSpeed {
+        actual,
+        min,
+        max,
+        target,
+        target_percentage,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{
+        KeyCategory, KeyDataType, M2_CPU_EFFICIENCY_CORE_1, M2_CPU_PERFORMANCE_CORE_1,
+        M2_CPU_PERFORMANCE_CORE_2, M2_CPU_PERFORMANCE_CORE_8, POWER_GPU_COMPUTED,
+    };
+    use std::collections::HashMap;
+
+    /// Serves fixed values and records which keys were read
+    struct FakeSource {
+        values: HashMap<&'static str, SmcData>,
+        reads: Vec<String>,
+    }
+
+    impl FakeSource {
+        fn new(values: &[(&'static str, SmcData)]) -> Self {
+            Self {
+                values: values.iter().cloned().collect(),
+                reads: Vec::new(),
+            }
+        }
+    }
+
+    impl SensorSource for FakeSource {
+        fn read(&mut self, key: &str) -> Result<SmcData> {
+            self.reads.push(key.to_string());
+            self.values
+                .get(key)
+                .cloned()
+                .ok_or_else(|| SmcError::KeyNotFound(key.to_string()))
+        }
+    }
+
+    #[test]
+    fn composite_reads_both_keys_and_multiplies() {
+        let mut source =
+            FakeSource::new(&[("IG0R", SmcData::Float(4.0)), ("VG0C", SmcData::Float(0.75))]);
+        let power = read_composite(&mut source, "gpu power (computed)").unwrap();
+        assert_eq!(power, 3.0);
+        assert_eq!(source.reads, [POWER_GPU_COMPUTED.a.key, POWER_GPU_COMPUTED.b.key]);
+    }
+
+    #[test]
+    fn typed_read_applies_the_key_scale() {
+        let key = SmcKey::new("VD0R", "DC In", KeyCategory::Power, KeyDataType::Voltage)
+            .with_scale(0.1);
+        let mut source = FakeSource::new(&[("VD0R", SmcData::Uint(120))]);
+        assert!(matches!(
+            read_typed(&mut source, &key),
+            Ok(SensorReading::Voltage(volt)) if volt == Volt(12.0)
+        ));
+    }
+
+    #[test]
+    fn composite_fails_on_missing_key_or_unknown_name() {
+        let mut source = FakeSource::new(&[("IG0R", SmcData::Float(4.0))]);
+        assert!(matches!(
+            read_composite(&mut source, "GPU Power (computed)"),
+            Err(SmcError::KeyNotFound(_))
+        ));
+        assert!(matches!(
+            read_composite(&mut source, "Fan Power"),
+            Err(SmcError::UnknownSensor(name)) if name == "Fan Power"
+        ));
+    }
+
+    #[test]
+    fn m2_core_temps_are_labeled_by_key_name() {
+        let mut source = FakeSource::new(&[
+            (M2_CPU_EFFICIENCY_CORE_1.key, SmcData::Float(40.0)),
+            (M2_CPU_PERFORMANCE_CORE_1.key, SmcData::Float(51.0)),
+            (M2_CPU_PERFORMANCE_CORE_2.key, SmcData::Float(52.0)),
+            (M2_CPU_PERFORMANCE_CORE_8.key, SmcData::Float(58.0)),
+        ]);
+        let temps = labeled_cpu_core_temps(&mut source, Platform::M2).unwrap();
+        assert_eq!(
+            temps,
+            [
+                (CoreKind::Efficiency, 1, Celsius(40.0)),
+                (CoreKind::Performance, 1, Celsius(51.0)),
+                (CoreKind::Performance, 2, Celsius(52.0)),
+                (CoreKind::Performance, 8, Celsius(58.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn every_m2_core_key_has_a_label() {
+        // The built-in list, as other tests register user keys without a label
+        for key in crate::keys::m2_cpu_temperature_keys() {
+            assert!(key.core_kind().is_some(), "{} has no core kind", key.name);
+            assert!(key.core_index().is_some(), "{} has no core index", key.name);
+        }
+        assert_eq!(M2_CPU_EFFICIENCY_CORE_1.core_index(), Some(1));
+    }
+}
@@ -1,9 +1,32 @@
 //! Data types and units for SMC values
 
-use std::{fmt, ops::Deref};
+use crate::{data::SmcData, error::SmcError, keys::KeyDataType};
+use std::{
+    cmp::Ordering,
+    collections::VecDeque,
+    convert::TryFrom,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    str::FromStr,
+    time::Duration,
+};
+
+/// Bit pattern of a float, with `-0.0` and `0.0` mapped to the same bits
+///
+/// Unit types that are map keys compare and hash by this, so `Eq` holds:
+/// a NaN equals itself, and only itself.
+fn f32_bits(value: f32) -> u32 {
+    if value == 0.0 {
+        0
+    } else {
+        value.to_bits()
+    }
+}
 
 /// Temperature in Celsius
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Celsius(pub f32);
 
 impl Deref for Celsius {
@@ -13,6 +36,84 @@ impl Deref for Celsius {
     }
 }
 
+impl Celsius {
+    /// Exclusive bounds of a plausible reading
+    ///
+    /// Absent sensors read 0 °C and broken ones return sentinels such as
+    /// -127 °C, so anything at or below the lower bound is discarded, as is
+    /// anything at or above 150 °C.
+    pub const PLAUSIBLE_RANGE: (f32, f32) = (0.0, 150.0);
+
+    /// Whether the reading lies within `PLAUSIBLE_RANGE`
+    ///
+    /// ```
+    /// # use macsmc::Celsius;
+    /// assert!(Celsius(42.0).is_plausible());
+    /// assert!(Celsius(0.1).is_plausible());
+    /// assert!(Celsius(149.9).is_plausible());
+    /// assert!(!Celsius(0.0).is_plausible());
+    /// assert!(!Celsius(-127.0).is_plausible());
+    /// assert!(!Celsius(150.0).is_plausible());
+    /// assert!(!Celsius(f32::NAN).is_plausible());
+    /// ```
+    pub fn is_plausible(&self) -> bool {
+        let (min, max) = Self::PLAUSIBLE_RANGE;
+        self.0 > min && self.0 < max
+    }
+
+    /// Decode raw SMC bytes of the given SMC data type as a temperature
+    ///
+    /// Useful for trying out captured bytes without a live SMC.
+    ///
+    /// ```
+    /// # use macsmc::Celsius;
+    /// let temp = Celsius::from_raw(&42.5_f32.to_le_bytes(), "flt ");
+    /// assert_eq!(temp, Some(Celsius(42.5)));
+    /// ```
+    pub fn from_raw(bytes: &[u8], data_type: &str) -> Option<Celsius> {
+        SmcData::parse(bytes, data_type).ok()?.as_temperature().ok()
+    }
+
+    /// Whether the value is within `epsilon` of `other`
+    ///
+    /// ```
+    /// # use macsmc::Celsius;
+    /// assert!(Celsius(42.01).approx_eq(&Celsius(42.0), 0.1));
+    /// ```
+    pub fn approx_eq(&self, other: &Celsius, epsilon: f32) -> bool {
+        (self.0 - other.0).abs() <= epsilon
+    }
+
+    /// Convert to absolute temperature
+    pub fn to_kelvin(&self) -> Kelvin {
+        Kelvin::from(*self)
+    }
+}
+
+impl PartialEq for Celsius {
+    fn eq(&self, other: &Self) -> bool {
+        f32_bits(self.0) == f32_bits(other.0)
+    }
+}
+
+impl Eq for Celsius {}
+
+impl PartialOrd for Celsius {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self == other {
+            Some(Ordering::Equal)
+        } else {
+            self.0.partial_cmp(&other.0)
+        }
+    }
+}
+
+impl Hash for Celsius {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        f32_bits(self.0).hash(state)
+    }
+}
+
 impl fmt::Display for Celsius {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:.1}°C", self.0)
@@ -21,6 +122,7 @@ impl fmt::Display for Celsius {
 
 /// Temperature in Fahrenheit
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Fahrenheit(pub f32);
 
 impl From<Celsius> for Fahrenheit {
@@ -35,8 +137,45 @@ impl fmt::Display for Fahrenheit {
     }
 }
 
+/// Absolute temperature in Kelvin
+///
+/// ```
+/// # use macsmc::{Celsius, Kelvin};
+/// assert_eq!(Kelvin::from(Celsius(0.0)), Kelvin(273.15));
+/// assert_eq!(Celsius::from(Kelvin(273.15)), Celsius(0.0));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Kelvin(pub f32);
+
+impl Deref for Kelvin {
+    type Target = f32;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Celsius> for Kelvin {
+    fn from(c: Celsius) -> Self {
+        Self(c.0 + 273.15)
+    }
+}
+
+impl From<Kelvin> for Celsius {
+    fn from(k: Kelvin) -> Self {
+        Self(k.0 - 273.15)
+    }
+}
+
+impl fmt::Display for Kelvin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1}K", self.0)
+    }
+}
+
 /// Voltage in Volts
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Volt(pub f32);
 
 impl Deref for Volt {
@@ -46,6 +185,13 @@ impl Deref for Volt {
     }
 }
 
+impl Volt {
+    /// Whether the value is within `epsilon` of `other`
+    pub fn approx_eq(&self, other: &Volt, epsilon: f32) -> bool {
+        (self.0 - other.0).abs() <= epsilon
+    }
+}
+
 impl fmt::Display for Volt {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:.3}V", self.0)
@@ -54,6 +200,7 @@ impl fmt::Display for Volt {
 
 /// Current in Amperes
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ampere(pub f32);
 
 impl Deref for Ampere {
@@ -70,7 +217,8 @@ impl fmt::Display for Ampere {
 }
 
 /// Power in Watts
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Watt(pub f32);
 
 impl Deref for Watt {
@@ -80,6 +228,42 @@ impl Deref for Watt {
     }
 }
 
+impl Watt {
+    /// Decode raw SMC bytes of the given SMC data type as power
+    pub fn from_raw(bytes: &[u8], data_type: &str) -> Option<Watt> {
+        SmcData::parse(bytes, data_type).ok()?.as_power().ok()
+    }
+
+    /// Whether the value is within `epsilon` of `other`
+    pub fn approx_eq(&self, other: &Watt, epsilon: f32) -> bool {
+        (self.0 - other.0).abs() <= epsilon
+    }
+}
+
+impl PartialEq for Watt {
+    fn eq(&self, other: &Self) -> bool {
+        f32_bits(self.0) == f32_bits(other.0)
+    }
+}
+
+impl Eq for Watt {}
+
+impl PartialOrd for Watt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self == other {
+            Some(Ordering::Equal)
+        } else {
+            self.0.partial_cmp(&other.0)
+        }
+    }
+}
+
+impl Hash for Watt {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        f32_bits(self.0).hash(state)
+    }
+}
+
 impl fmt::Display for Watt {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:.2}W", self.0)
@@ -87,7 +271,8 @@ impl fmt::Display for Watt {
 }
 
 /// Fan speed in RPM
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rpm(pub f32);
 
 impl Deref for Rpm {
@@ -97,14 +282,180 @@ impl Deref for Rpm {
     }
 }
 
+impl Rpm {
+    /// Decode raw SMC bytes of the given SMC data type as a fan speed
+    pub fn from_raw(bytes: &[u8], data_type: &str) -> Option<Rpm> {
+        SmcData::parse(bytes, data_type).ok()?.as_rpm().ok()
+    }
+
+    /// Whether the value is within `epsilon` of `other`
+    pub fn approx_eq(&self, other: &Rpm, epsilon: f32) -> bool {
+        (self.0 - other.0).abs() <= epsilon
+    }
+}
+
+impl PartialEq for Rpm {
+    fn eq(&self, other: &Self) -> bool {
+        f32_bits(self.0) == f32_bits(other.0)
+    }
+}
+
+impl Eq for Rpm {}
+
+impl PartialOrd for Rpm {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self == other {
+            Some(Ordering::Equal)
+        } else {
+            self.0.partial_cmp(&other.0)
+        }
+    }
+}
+
+impl Hash for Rpm {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        f32_bits(self.0).hash(state)
+    }
+}
+
 impl fmt::Display for Rpm {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:.0} RPM", self.0)
     }
 }
 
+/// Speeds of a single fan
+///
+/// With the `serde` feature, this and the other value types convert to and
+/// from JSON, with units as plain numbers:
+///
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use macsmc::{FanSpeed, Rpm};
+///
+/// let speed = FanSpeed {
+///     actual: Rpm(1200.0),
+///     min: Rpm(1000.0),
+///     max: Rpm(6000.0),
+///     target: Rpm(1200.0),
+///     target_percentage: None,
+/// };
+/// let json = serde_json::to_string(&speed).unwrap();
+/// assert!(json.contains(r#""actual":1200.0"#));
+/// assert_eq!(serde_json::from_str::<FanSpeed>(&json).unwrap(), speed);
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FanSpeed {
+    /// Current speed
+    pub actual: Rpm,
+    /// Minimum speed
+    pub min: Rpm,
+    /// Maximum speed
+    pub max: Rpm,
+    /// Speed the fan is driven towards
+    ///
+    /// For fans with a PWM target this is `target_percentage` mapped onto
+    /// `min..=max`.
+    pub target: Rpm,
+    /// Target duty cycle, for fans whose target key is a PWM value
+    ///
+    /// Most Macs report the target in RPM (`fpe2` on Intel, `flt ` on Apple
+    /// Silicon), but some firmware reports a `ui8` 0-255 duty cycle instead.
+    pub target_percentage: Option<Percentage>,
+}
+
+impl FanSpeed {
+    /// Slack in RPM when comparing speeds against the minimum
+    pub const STALL_EPSILON: f32 = 50.0;
+
+    /// Whether the fan is asked to spin but barely moves, which hints at a seized fan
+    ///
+    /// A fan that is deliberately off has a target at (or near) its minimum
+    /// and is not considered stalled.
+    pub fn is_stalled(&self) -> bool {
+        let threshold = self.min.0 + Self::STALL_EPSILON;
+        self.target.0 > threshold && self.actual.0 < threshold
+    }
+
+    /// Current speed within `min..=max`, `None` if the range is unknown
+    pub fn percentage(&self) -> Option<Percentage> {
+        let range = self.max.0 - self.min.0;
+        if range <= 0.0 || range.is_nan() {
+            return None;
+        }
+        let pct = (self.actual.0 - self.min.0) / range * 100.0;
+        Some(Percentage(pct.clamp(0.0, 100.0)))
+    }
+
+    /// Speed at `pct` percent of the way from `min` to `max`
+    pub fn rpm_at(&self, pct: f32) -> Rpm {
+        Rpm(self.min.0 + (self.max.0 - self.min.0) * pct / 100.0)
+    }
+}
+
+/// Who controls the speed of a fan
+///
+/// Displays and parses as `auto` or `forced`:
+///
+/// ```
+/// use macsmc::FanMode;
+///
+/// assert_eq!("Forced".parse::<FanMode>(), Ok(FanMode::Forced));
+/// assert_eq!(FanMode::Auto.to_string(), "auto");
+/// assert!("manual".parse::<FanMode>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum FanMode {
+    /// The SMC picks the speed
+    Auto,
+    /// The fan follows a manually set target
+    Forced,
+}
+
+impl fmt::Display for FanMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FanMode::Auto => write!(f, "auto"),
+            FanMode::Forced => write!(f, "forced"),
+        }
+    }
+}
+
+impl FromStr for FanMode {
+    type Err = String;
+
+    /// Parse `auto` or `forced`, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(FanMode::Auto),
+            "forced" => Ok(FanMode::Forced),
+            _ => Err(format!("unknown fan mode: {} (expected auto or forced)", s)),
+        }
+    }
+}
+
+/// Connected power adapter
+///
+/// Each field is `None` if the machine doesn't report it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AdapterInfo {
+    /// Rated wattage of the adapter (e.g. 67W, 96W)
+    pub wattage: Option<Watt>,
+    /// Voltage at the DC input
+    pub voltage: Option<Volt>,
+    /// Current drawn at the DC input
+    pub current: Option<Ampere>,
+}
+
 /// Percentage value
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Percentage(pub f32);
 
 impl Deref for Percentage {
@@ -118,4 +469,427 @@ impl fmt::Display for Percentage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:.1}%", self.0)
     }
-}
\ No newline at end of file
+}
+
+/// Format a time estimate as hours and minutes, e.g. "2h 35m" or "40m"
+///
+/// `None` (no estimate) is shown as "—", and estimates of 100 hours or more,
+/// which the SMC reports while still calculating, as ">99h".
+///
+/// ```
+/// # use macsmc::fmt_hm;
+/// # use std::time::Duration;
+/// assert_eq!(fmt_hm(Some(Duration::from_secs(9300))), "2h 35m");
+/// assert_eq!(fmt_hm(Some(Duration::from_secs(2400))), "40m");
+/// assert_eq!(fmt_hm(None), "—");
+/// ```
+pub fn fmt_hm(duration: Option<Duration>) -> String {
+    let minutes = match duration {
+        Some(duration) => duration.as_secs() / 60,
+        None => return "—".to_string(),
+    };
+
+    let (hours, minutes) = (minutes / 60, minutes % 60);
+    if hours >= 100 {
+        ">99h".to_string()
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// Direction of a value over recent samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrendDir {
+    /// Values are increasing
+    Rising,
+    /// Values are decreasing
+    Falling,
+    /// Values are flat (within the threshold)
+    Stable,
+}
+
+impl fmt::Display for TrendDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrendDir::Rising => write!(f, "↑"),
+            TrendDir::Falling => write!(f, "↓"),
+            TrendDir::Stable => write!(f, "→"),
+        }
+    }
+}
+
+/// Tracks the direction of a value over a bounded window of samples
+///
+/// Unit types can be pushed by dereferencing them, e.g. `trend.push(*temp)`.
+#[derive(Debug, Clone)]
+pub struct Trend {
+    samples: VecDeque<f32>,
+    window: usize,
+    threshold: f32,
+}
+
+impl Trend {
+    /// Create a tracker over the last `window` samples
+    ///
+    /// A slope (change per sample) within `threshold` counts as stable.
+    pub fn new(window: usize, threshold: f32) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(window),
+            window: window.max(2),
+            threshold: threshold.abs(),
+        }
+    }
+
+    /// Add a sample, dropping the oldest one if the window is full
+    pub fn push(&mut self, value: f32) {
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(value);
+    }
+
+    /// Direction based on the least-squares slope of the samples
+    pub fn direction(&self) -> TrendDir {
+        let slope = self.slope();
+        if slope > self.threshold {
+            TrendDir::Rising
+        } else if slope < -self.threshold {
+            TrendDir::Falling
+        } else {
+            TrendDir::Stable
+        }
+    }
+
+    fn slope(&self) -> f32 {
+        let n = self.samples.len();
+        if n < 2 {
+            return 0.0;
+        }
+
+        let mean_x = (n - 1) as f32 / 2.0;
+        let mean_y = self.samples.iter().sum::<f32>() / n as f32;
+        let (mut num, mut den) = (0.0, 0.0);
+        for (x, y) in self.samples.iter().enumerate() {
+            let dx = x as f32 - mean_x;
+            num += dx * (y - mean_y);
+            den += dx * dx;
+        }
+        num / den
+    }
+}
+
+/// Lowest, highest and latest value seen, e.g. session peaks of a sensor
+///
+/// ```
+/// use macsmc::{Celsius, MinMax};
+///
+/// let mut temp = MinMax::new(Celsius(50.0));
+/// temp.update(Celsius(72.5));
+/// temp.update(Celsius(61.0));
+/// assert_eq!((temp.min, temp.max, temp.current), (Celsius(50.0), Celsius(72.5), Celsius(61.0)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MinMax<T> {
+    /// Lowest value
+    pub min: T,
+    /// Highest value
+    pub max: T,
+    /// Latest value
+    pub current: T,
+}
+
+impl<T: PartialOrd + Copy> MinMax<T> {
+    /// Start tracking from a first value
+    pub fn new(value: T) -> Self {
+        Self {
+            min: value,
+            max: value,
+            current: value,
+        }
+    }
+
+    /// Record a new value
+    ///
+    /// Values that don't compare (NaN) only replace `current`. If tracking
+    /// started from such a value, min and max are seeded from the first value
+    /// that does.
+    pub fn update(&mut self, value: T) {
+        if !is_comparable(self.min) || value < self.min {
+            self.min = value;
+        }
+        if !is_comparable(self.max) || value > self.max {
+            self.max = value;
+        }
+        self.current = value;
+    }
+}
+
+// False for a float NaN, which isn't even equal to itself
+fn is_comparable<T: PartialOrd>(value: T) -> bool {
+    value.partial_cmp(&value).is_some()
+}
+
+impl TryFrom<SmcData> for Celsius {
+    type Error = SmcError;
+    fn try_from(data: SmcData) -> Result<Self, Self::Error> {
+        data.as_temperature()
+    }
+}
+
+impl TryFrom<SmcData> for Volt {
+    type Error = SmcError;
+    fn try_from(data: SmcData) -> Result<Self, Self::Error> {
+        data.as_voltage()
+    }
+}
+
+impl TryFrom<SmcData> for Ampere {
+    type Error = SmcError;
+    fn try_from(data: SmcData) -> Result<Self, Self::Error> {
+        data.as_current()
+    }
+}
+
+impl TryFrom<SmcData> for Watt {
+    type Error = SmcError;
+    fn try_from(data: SmcData) -> Result<Self, Self::Error> {
+        data.as_power()
+    }
+}
+
+impl TryFrom<SmcData> for Rpm {
+    type Error = SmcError;
+    fn try_from(data: SmcData) -> Result<Self, Self::Error> {
+        data.as_rpm()
+    }
+}
+
+impl TryFrom<SmcData> for Percentage {
+    type Error = SmcError;
+    fn try_from(data: SmcData) -> Result<Self, Self::Error> {
+        data.as_percentage()
+    }
+}
+
+/// A decoded reading typed by the kind of its sensor
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum SensorReading {
+    /// Temperature reading
+    Temperature(Celsius),
+    /// Voltage reading
+    Voltage(Volt),
+    /// Current reading
+    Current(Ampere),
+    /// Power reading
+    Power(Watt),
+    /// Fan speed reading
+    FanSpeed(Rpm),
+    /// Flags, generic numbers and strings
+    Raw(SmcData),
+}
+
+impl SensorReading {
+    /// Convert data according to the data type of its key
+    pub fn from_data(data: SmcData, data_type: &KeyDataType) -> Result<Self, SmcError> {
+        Ok(match data_type {
+            KeyDataType::Temperature => SensorReading::Temperature(data.as_temperature()?),
+            KeyDataType::Voltage => SensorReading::Voltage(data.as_voltage()?),
+            KeyDataType::Current => SensorReading::Current(data.as_current()?),
+            KeyDataType::Power => SensorReading::Power(data.as_power()?),
+            KeyDataType::FanSpeed => SensorReading::FanSpeed(data.as_rpm()?),
+            KeyDataType::Flag | KeyDataType::Numeric | KeyDataType::String => {
+                SensorReading::Raw(data)
+            }
+        })
+    }
+
+    /// Numeric value of the reading, `None` for strings and raw bytes
+    ///
+    /// Flags are `1.0` when set and `0.0` otherwise.
+    pub fn value_f64(&self) -> Option<f64> {
+        match self {
+            SensorReading::Temperature(v) => Some(v.0 as f64),
+            SensorReading::Voltage(v) => Some(v.0 as f64),
+            SensorReading::Current(v) => Some(v.0 as f64),
+            SensorReading::Power(v) => Some(v.0 as f64),
+            SensorReading::FanSpeed(v) => Some(v.0 as f64),
+            SensorReading::Raw(SmcData::Flag(b)) => Some(if *b { 1.0 } else { 0.0 }),
+            SensorReading::Raw(data) => data.as_f64(),
+        }
+    }
+
+    /// Unit symbol of the reading, empty for raw values
+    pub fn unit(&self) -> &'static str {
+        match self {
+            SensorReading::Temperature(_) => "°C",
+            SensorReading::Voltage(_) => "V",
+            SensorReading::Current(_) => "A",
+            SensorReading::Power(_) => "W",
+            SensorReading::FanSpeed(_) => "RPM",
+            SensorReading::Raw(_) => "",
+        }
+    }
+}
+
+impl fmt::Display for SensorReading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SensorReading::Temperature(v) => write!(f, "{}", v),
+            SensorReading::Voltage(v) => write!(f, "{}", v),
+            SensorReading::Current(v) => write!(f, "{}", v),
+            SensorReading::Power(v) => write!(f, "{}", v),
+            SensorReading::FanSpeed(v) => write!(f, "{}", v),
+            SensorReading::Raw(data) => write!(f, "{}", data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn float_units_dedup_in_hash_set() {
+        let temps: HashSet<Celsius> = [
+            Celsius(42.0),
+            Celsius(42.0),
+            Celsius(0.0),
+            Celsius(-0.0),
+            Celsius(f32::NAN),
+            Celsius(f32::NAN),
+        ]
+        .iter()
+        .copied()
+        .collect();
+        assert_eq!(temps.len(), 3);
+        assert!(temps.contains(&Celsius(f32::NAN)));
+
+        let powers: HashSet<Watt> = [Watt(1.5), Watt(1.5), Watt(-0.0)].iter().copied().collect();
+        assert_eq!(powers.len(), 2);
+        let speeds: HashSet<Rpm> = [Rpm(1200.0), Rpm(0.0), Rpm(-0.0)].iter().copied().collect();
+        assert_eq!(speeds.len(), 2);
+    }
+
+    #[test]
+    fn float_units_order_consistently_with_eq() {
+        assert_eq!(Celsius(f32::NAN).partial_cmp(&Celsius(f32::NAN)), Some(Ordering::Equal));
+        assert_eq!(Celsius(f32::NAN).partial_cmp(&Celsius(1.0)), None);
+        assert_eq!(Celsius(-0.0).partial_cmp(&Celsius(0.0)), Some(Ordering::Equal));
+        assert!(Rpm(1000.0) < Rpm(2000.0));
+    }
+
+    #[test]
+    fn try_from_smc_data() {
+        assert_eq!(Celsius::try_from(SmcData::Float(42.5)).unwrap(), Celsius(42.5));
+        assert_eq!(Volt::try_from(SmcData::Uint(12_000)).unwrap(), Volt(12.0));
+        assert_eq!(Rpm::try_from(SmcData::Float(1200.0)).unwrap(), Rpm(1200.0));
+        assert!(matches!(
+            Celsius::try_from(SmcData::String("hot".to_string())),
+            Err(SmcError::DataError { .. })
+        ));
+        assert!(Watt::try_from(SmcData::Raw(vec![0, 1])).is_err());
+    }
+
+    fn fan(actual: f32, target: f32) -> FanSpeed {
+        FanSpeed {
+            actual: Rpm(actual),
+            min: Rpm(1000.0),
+            max: Rpm(6000.0),
+            target: Rpm(target),
+            target_percentage: None,
+        }
+    }
+
+    #[test]
+    fn spinning_fan_is_not_stalled() {
+        assert!(!fan(2400.0, 2500.0).is_stalled());
+    }
+
+    #[test]
+    fn fan_off_at_its_minimum_is_not_stalled() {
+        assert!(!fan(0.0, 1000.0).is_stalled());
+        assert!(!fan(0.0, 1000.0 + FanSpeed::STALL_EPSILON).is_stalled());
+    }
+
+    #[test]
+    fn fan_asked_to_spin_but_standing_still_is_stalled() {
+        assert!(fan(0.0, 2500.0).is_stalled());
+        assert!(fan(1020.0, 3000.0).is_stalled());
+    }
+
+    #[test]
+    fn rpm_at_percentage_of_range() {
+        let speed = FanSpeed {
+            min: Rpm(1000.0),
+            max: Rpm(5000.0),
+            ..fan(0.0, 0.0)
+        };
+        assert_eq!(speed.rpm_at(50.0), Rpm(3000.0));
+        assert_eq!(speed.rpm_at(0.0), Rpm(1000.0));
+        assert_eq!(speed.rpm_at(100.0), Rpm(5000.0));
+    }
+
+    #[test]
+    fn min_max_skips_nan() {
+        let mut peak = MinMax::new(50.0_f64);
+        peak.update(f64::NAN);
+        peak.update(70.0);
+        peak.update(f64::NAN);
+        assert_eq!((peak.min, peak.max), (50.0, 70.0));
+        assert!(peak.current.is_nan());
+    }
+
+    #[test]
+    fn min_max_seeds_from_first_comparable_value() {
+        let mut peak = MinMax::new(f64::NAN);
+        peak.update(f64::NAN);
+        peak.update(60.0);
+        peak.update(40.0);
+        assert_eq!((peak.min, peak.max, peak.current), (40.0, 60.0, 40.0));
+    }
+
+    fn trend_of(window: usize, samples: &[f32]) -> Trend {
+        let mut trend = Trend::new(window, 0.1);
+        for &sample in samples {
+            trend.push(sample);
+        }
+        trend
+    }
+
+    #[test]
+    fn trend_rising() {
+        let trend = trend_of(5, &[40.0, 41.0, 41.5, 43.0, 44.0]);
+        assert_eq!(trend.direction(), TrendDir::Rising);
+    }
+
+    #[test]
+    fn trend_falling() {
+        let trend = trend_of(5, &[60.0, 58.0, 58.5, 55.0, 54.0]);
+        assert_eq!(trend.direction(), TrendDir::Falling);
+    }
+
+    #[test]
+    fn trend_flat_within_threshold() {
+        let trend = trend_of(5, &[50.0, 50.05, 49.95, 50.0, 50.1]);
+        assert_eq!(trend.direction(), TrendDir::Stable);
+    }
+
+    #[test]
+    fn trend_needs_two_samples() {
+        assert_eq!(trend_of(5, &[]).direction(), TrendDir::Stable);
+        assert_eq!(trend_of(5, &[80.0]).direction(), TrendDir::Stable);
+    }
+
+    #[test]
+    fn trend_only_sees_the_window() {
+        // The early drop has left the window, leaving only the climb
+        let trend = trend_of(3, &[90.0, 40.0, 41.0, 42.0]);
+        assert_eq!(trend.direction(), TrendDir::Rising);
+    }
+}
@@ -7,8 +7,8 @@ use std::{collections::HashMap, fs, path::PathBuf};
 /// Main configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
-    /// InfluxDB configuration
-    pub influx: InfluxConfig,
+    /// InfluxDB configuration (optional when monitoring to stdout)
+    pub influx: Option<InfluxConfig>,
     /// System hostname/identifier
     pub hostname: Option<String>,
     /// Collection interval in seconds
@@ -30,6 +30,21 @@ pub struct MetricsConfig {
     pub power: bool,
     /// Collect fan speeds
     pub fans: bool,
+    /// Only collect these fans (by index); all fans if unset
+    #[serde(default)]
+    pub fan_indices: Option<Vec<u8>>,
+    /// Omit power readings of exactly 0.0 W (sensors the machine lacks)
+    #[serde(default)]
+    pub skip_zero_power: bool,
+    /// Collect CPU/GPU voltage rails
+    #[serde(default)]
+    pub voltage: bool,
+    /// Collect CPU/GPU currents
+    #[serde(default)]
+    pub current: bool,
+    /// Derived metrics by name, computed from expressions over SMC keys (e.g. "PCPT + PG0R")
+    #[serde(default)]
+    pub derived: HashMap<String, String>,
 }
 
 impl Default for Config {
@@ -40,7 +55,7 @@ impl Default for Config {
             .unwrap_or_else(|| "unknown".to_string());
 
         Self {
-            influx: InfluxConfig::default(),
+            influx: Some(InfluxConfig::default()),
             hostname: Some(hostname),
             interval: Some(30),
             metrics: MetricsConfig::default(),
@@ -56,6 +71,29 @@ impl Default for MetricsConfig {
             system_temp: true,
             power: true,
             fans: true,
+            fan_indices: None,
+            skip_zero_power: false,
+            voltage: false,
+            current: false,
+            derived: HashMap::new(),
+        }
+    }
+}
+
+impl MetricsConfig {
+    /// Configuration with every metric disabled
+    pub fn none() -> Self {
+        Self {
+            cpu_temp: false,
+            gpu_temp: false,
+            system_temp: false,
+            power: false,
+            fans: false,
+            fan_indices: None,
+            skip_zero_power: false,
+            voltage: false,
+            current: false,
+            derived: HashMap::new(),
         }
     }
 }
@@ -75,7 +113,6 @@ impl Config {
     /// Load configuration from file
     pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
         let config_path = Self::config_path()?;
-        println!("Loading config from: {:?}", config_path);
         if config_path.exists() {
             let content = fs::read_to_string(&config_path)?;
             let config: Config = toml::from_str(&content)?;
@@ -116,7 +153,7 @@ impl Config {
         influx.tags = Some(tags);
 
         Self {
-            influx,
+            influx: Some(influx),
             hostname: Some("your_mac".to_string()),  
             interval: Some(30),
             metrics: MetricsConfig {
@@ -125,6 +162,11 @@ impl Config {
                 system_temp: true,
                 power: true,
                 fans: true,
+                fan_indices: None,
+                skip_zero_power: false,
+                voltage: false,
+                current: false,
+                derived: HashMap::new(),
             },
         }
     }
@@ -0,0 +1,103 @@
+//! CSV time series output for macstats
+
+use crate::influx::Metric;
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+const HEADER: &str = "timestamp,measurement,field,tags,value";
+
+/// Appends metrics to a CSV file, one row per metric
+pub struct CsvWriter {
+    writer: BufWriter<File>,
+}
+
+impl CsvWriter {
+    /// Open a CSV file for appending, writing the header if the file is new or empty
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let is_empty = file.metadata()?.len() == 0;
+
+        let mut writer = BufWriter::new(file);
+        if is_empty {
+            writeln!(writer, "{}", HEADER)?;
+            writer.flush()?;
+        }
+
+        Ok(Self { writer })
+    }
+
+    /// Append one interval of metrics sharing a timestamp (nanoseconds) and flush
+    pub fn write_interval(&mut self, timestamp: u64, metrics: &[Metric]) -> io::Result<()> {
+        for metric in metrics {
+            let mut tags: Vec<String> = metric
+                .tags
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect();
+            tags.sort();
+
+            writeln!(
+                self.writer,
+                "{},{},{},{},{}",
+                timestamp,
+                escape_field(&metric.measurement),
+                escape_field(&metric.field),
+                escape_field(&tags.join(";")),
+                metric.value
+            )?;
+        }
+
+        // Flush every interval so killed captures don't lose buffered rows
+        self.writer.flush()
+    }
+}
+
+fn escape_field(s: &str) -> String {
+    if s.contains(',') || s.contains('"') || s.contains('\n') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    #[test]
+    fn intervals_share_one_header() {
+        let path = env::temp_dir().join(format!("macstats-csv-{}.csv", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut writer = CsvWriter::open(&path).unwrap();
+        writer
+            .write_interval(1, &[Metric::new("fan", "actual", 1200.0).tag("fan", "0")])
+            .unwrap();
+        writer
+            .write_interval(2, &[Metric::new("fan", "max", 6000.0).tag("fan", "0")])
+            .unwrap();
+        drop(writer);
+        // Reopening a non-empty file doesn't add another header either
+        CsvWriter::open(&path)
+            .unwrap()
+            .write_interval(3, &[Metric::new("power", "value", 1.5)])
+            .unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                HEADER,
+                "1,fan,actual,fan=0,1200",
+                "2,fan,max,fan=0,6000",
+                "3,power,value,,1.5",
+            ]
+        );
+    }
+}
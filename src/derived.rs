@@ -0,0 +1,300 @@
+//! Derived metrics computed from simple arithmetic over SMC keys
+//!
+//! Expressions combine keys and numbers with `+ - * /` and parentheses,
+//! e.g. `"PCPT + PG0R"` or `"(TC0P + TG0P) / 2"`.
+
+use macsmc::SensorSource;
+use std::fmt;
+
+/// Error parsing or evaluating an expression
+#[derive(Debug)]
+pub struct ExprError(String);
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+type Result<T> = std::result::Result<T, ExprError>;
+
+/// Parsed arithmetic expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Constant number
+    Number(f64),
+    /// Value of an SMC key
+    Key(String),
+    /// Negated expression
+    Neg(Box<Expr>),
+    /// Binary operation
+    Binary(Box<Expr>, Op, Box<Expr>),
+}
+
+/// Binary operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// Addition
+    Add,
+    /// Subtraction
+    Sub,
+    /// Multiplication
+    Mul,
+    /// Division
+    Div,
+}
+
+impl Expr {
+    /// Parse an expression
+    pub fn parse(input: &str) -> Result<Self> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.expr()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(ExprError(format!("unexpected {}", token))),
+        }
+    }
+
+    /// Evaluate the expression, reading keys from `source`
+    ///
+    /// Fails if a key can't be read or isn't numeric, or on division by zero.
+    pub fn eval(&self, source: &mut dyn SensorSource) -> Result<f64> {
+        match self {
+            Expr::Number(n) => Ok(*n),
+            Expr::Key(key) => match source.read(key) {
+                Ok(data) => data
+                    .as_f64()
+                    .ok_or_else(|| ExprError(format!("key {} is not numeric", key))),
+                Err(e) => Err(ExprError(format!("key {}: {}", key, e))),
+            },
+            Expr::Neg(expr) => Ok(-expr.eval(source)?),
+            Expr::Binary(lhs, op, rhs) => {
+                let (lhs, rhs) = (lhs.eval(source)?, rhs.eval(source)?);
+                match op {
+                    Op::Add => Ok(lhs + rhs),
+                    Op::Sub => Ok(lhs - rhs),
+                    Op::Mul => Ok(lhs * rhs),
+                    Op::Div if rhs == 0.0 => Err(ExprError("division by zero".to_string())),
+                    Op::Div => Ok(lhs / rhs),
+                }
+            }
+        }
+    }
+}
+
+/// Parse and evaluate an expression in one go
+pub fn evaluate(expression: &str, source: &mut dyn SensorSource) -> Result<f64> {
+    Expr::parse(expression)?.eval(source)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Key(String),
+    Op(char),
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "number {}", n),
+            Token::Key(key) => write!(f, "key {}", key),
+            Token::Op(op) => write!(f, "'{}'", op),
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '+' | '-' | '*' | '/' => {
+                tokens.push(Token::Op(c));
+                chars.next();
+            }
+            '(' => {
+                tokens.push(Token::Open);
+                chars.next();
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                chars.next();
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_digit() || c == '.') {
+                        break;
+                    }
+                    number.push(c);
+                    chars.next();
+                }
+                let value = number
+                    .parse()
+                    .map_err(|_| ExprError(format!("invalid number {}", number)))?;
+                tokens.push(Token::Number(value));
+            }
+            c if c.is_ascii_alphanumeric() || c == '#' => {
+                let mut key = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '#') {
+                        break;
+                    }
+                    key.push(c);
+                    chars.next();
+                }
+                if key.len() != 4 {
+                    return Err(ExprError(format!("key must be 4 characters, got {}", key)));
+                }
+                tokens.push(Token::Key(key));
+            }
+            c => return Err(ExprError(format!("unexpected character '{}'", c))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    // expr = term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<Expr> {
+        let mut lhs = self.term()?;
+        while let Some(Token::Op(c @ ('+' | '-'))) = self.peek() {
+            let op = if *c == '+' { Op::Add } else { Op::Sub };
+            self.pos += 1;
+            lhs = Expr::Binary(Box::new(lhs), op, Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+
+    // term = factor (('*' | '/') factor)*
+    fn term(&mut self) -> Result<Expr> {
+        let mut lhs = self.factor()?;
+        while let Some(Token::Op(c @ ('*' | '/'))) = self.peek() {
+            let op = if *c == '*' { Op::Mul } else { Op::Div };
+            self.pos += 1;
+            lhs = Expr::Binary(Box::new(lhs), op, Box::new(self.factor()?));
+        }
+        Ok(lhs)
+    }
+
+    // factor = '-' factor | number | key | '(' expr ')'
+    fn factor(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Op('-')) => Ok(Expr::Neg(Box::new(self.factor()?))),
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Key(key)) => Ok(Expr::Key(key)),
+            Some(Token::Open) => {
+                let expr = self.expr()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err(ExprError("missing ')'".to_string())),
+                }
+            }
+            Some(token) => Err(ExprError(format!("unexpected {}", token))),
+            None => Err(ExprError("unexpected end of expression".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macsmc::{SmcData, SmcError};
+    use std::collections::HashMap;
+
+    struct FakeSource(HashMap<&'static str, SmcData>);
+
+    impl SensorSource for FakeSource {
+        fn read(&mut self, key: &str) -> macsmc::Result<SmcData> {
+            self.0
+                .get(key)
+                .cloned()
+                .ok_or_else(|| SmcError::KeyNotFound(key.to_string()))
+        }
+    }
+
+    fn source() -> FakeSource {
+        FakeSource(
+            [
+                ("PCPT", SmcData::Float(12.5)),
+                ("PG0R", SmcData::Float(7.5)),
+                ("TC0P", SmcData::Uint(4)),
+                ("ZERO", SmcData::Int(0)),
+                ("NAME", SmcData::String("M2".to_string())),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+        )
+    }
+
+    #[test]
+    fn adds_two_keys() {
+        assert_eq!(evaluate("PCPT + PG0R", &mut source()).unwrap(), 20.0);
+    }
+
+    #[test]
+    fn missing_key_fails() {
+        let err = evaluate("PCPT + MISS", &mut source()).unwrap_err();
+        assert!(err.to_string().contains("MISS"));
+    }
+
+    #[test]
+    fn non_numeric_key_fails() {
+        assert!(evaluate("NAME * 2", &mut source()).is_err());
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        assert_eq!(evaluate("2 + TC0P * 3", &mut source()).unwrap(), 14.0);
+        assert_eq!(evaluate("(2 + TC0P) * 3", &mut source()).unwrap(), 18.0);
+        assert_eq!(evaluate("10 - 4 - 3", &mut source()).unwrap(), 3.0);
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(evaluate("-TC0P + 1", &mut source()).unwrap(), -3.0);
+        assert_eq!(evaluate("2 * -(PG0R - PCPT)", &mut source()).unwrap(), 10.0);
+    }
+
+    #[test]
+    fn division_by_zero_fails() {
+        let err = evaluate("PCPT / ZERO", &mut source()).unwrap_err();
+        assert_eq!(err.to_string(), "division by zero");
+    }
+
+    #[test]
+    fn unbalanced_parenthesis_fails() {
+        let err = evaluate("(PCPT + PG0R", &mut source()).unwrap_err();
+        assert_eq!(err.to_string(), "missing ')'");
+        assert!(Expr::parse("PCPT + PG0R)").is_err());
+    }
+}
@@ -0,0 +1,392 @@
+//! Setup diagnostics for `macstats doctor`
+
+use crate::{
+    config::Config,
+    influx::{InfluxClient, InfluxError},
+};
+use macsmc::{keys::cpu_temperature_keys, platform, Platform, SensorSource, SmcError};
+use std::{fmt, fs, path::Path};
+
+/// Outcome of a single diagnostic check
+#[derive(Debug, Clone)]
+pub struct Check {
+    /// What was checked
+    pub name: &'static str,
+    /// Whether the check passed
+    pub passed: bool,
+    /// Short description of the result
+    pub detail: String,
+    /// How to fix a failed check
+    pub hint: Option<&'static str>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            passed: true,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: &'static str) -> Self {
+        Self {
+            name,
+            passed: false,
+            detail: detail.into(),
+            hint: Some(hint),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mark = if self.passed { "✓" } else { "✗" };
+        write!(f, "{} {}: {}", mark, self.name, self.detail)?;
+        if let Some(hint) = self.hint {
+            write!(f, "\n    → {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run all checks in order
+///
+/// Checks that depend on an earlier one (sensors on the SMC, InfluxDB on the
+/// config) are left out when it fails.
+pub async fn diagnose() -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    match macsmc::connect() {
+        Ok(mut smc) => {
+            checks.push(check_smc(None));
+
+            let detected = smc.platform();
+            checks.push(check_platform(detected, probe_platform(&mut smc)));
+
+            let platform = smc.key_platform();
+            let total = cpu_temperature_keys(platform).len();
+            checks.push(check_sensors(responding_cpu_keys(&mut smc, platform), total));
+        }
+        Err(e) => {
+            checks.push(check_smc(Some(&e)));
+            checks.push(check_platform(platform::detect_platform(), None));
+        }
+    }
+
+    match Config::config_path() {
+        Ok(path) => {
+            let check = check_config(&path);
+            let valid = check.passed;
+            checks.push(check);
+
+            if valid {
+                if let Ok(Some(influx)) = Config::load().map(|config| config.influx) {
+                    let result = match InfluxClient::new(influx) {
+                        Ok(client) => client.test_connection().await,
+                        Err(e) => Err(e),
+                    };
+                    checks.push(check_influx(&result));
+                }
+            }
+        }
+        Err(e) => checks.push(Check::fail(
+            "Config file",
+            e.to_string(),
+            "make sure your home directory is set up correctly",
+        )),
+    }
+
+    checks
+}
+
+/// Check the result of opening the SMC connection
+pub fn check_smc(error: Option<&SmcError>) -> Check {
+    const NAME: &str = "SMC connection";
+    match error {
+        None => Check::pass(NAME, "connected"),
+        Some(SmcError::InsufficientPrivileges) => Check::fail(
+            NAME,
+            "insufficient privileges",
+            "run macstats with sudo",
+        ),
+        Some(SmcError::NotAvailable) => Check::fail(
+            NAME,
+            "AppleSMC service not found",
+            "macstats only works on real Mac hardware, not in VMs or containers",
+        ),
+        Some(e) if e.is_transient() => Check::fail(
+            NAME,
+            e.to_string(),
+            "the SMC is busy; try again in a moment",
+        ),
+        Some(e) => Check::fail(
+            NAME,
+            e.to_string(),
+            "try running with sudo; if that fails, please report an issue",
+        ),
+    }
+}
+
+/// Find the platform whose CPU temperature keys respond best
+///
+/// Returns `None` if none of the known keys produce a reading.
+pub fn probe_platform(source: &mut dyn SensorSource) -> Option<Platform> {
+    let candidates = [
+        Platform::Intel,
+        Platform::M1,
+        Platform::M2,
+        Platform::M3,
+        Platform::M4,
+    ];
+
+    candidates
+        .iter()
+        .map(|&platform| (platform, responding_cpu_keys(source, platform)))
+        .filter(|&(_, count)| count > 0)
+        .max_by_key(|&(_, count)| count)
+        .map(|(platform, _)| platform)
+}
+
+/// Check that the detected platform agrees with the probed one
+///
+/// Only the chip generation is compared, since variants share their keys.
+pub fn check_platform(detected: Option<Platform>, probed: Option<Platform>) -> Check {
+    const NAME: &str = "Platform detection";
+    match (detected, probed) {
+        (Some(detected), Some(probed)) if detected.generation() == probed.generation() => {
+            Check::pass(NAME, format!("{}", detected))
+        }
+        (Some(detected), Some(probed)) => Check::fail(
+            NAME,
+            format!("detected {} but keys match {}", detected, probed),
+            "readings may be incomplete; please report your chip and macOS version",
+        ),
+        (Some(detected), None) => Check::fail(
+            NAME,
+            format!("detected {} but no known keys respond", detected),
+            "add the keys for your machine to sensors.toml",
+        ),
+        (None, Some(probed)) => Check::fail(
+            NAME,
+            format!("unknown chip, keys match {}", probed),
+            "please report your CPU brand string (sysctl machdep.cpu.brand_string)",
+        ),
+        (None, None) => Check::fail(
+            NAME,
+            "unknown chip and no known keys respond",
+            "please report your CPU brand string (sysctl machdep.cpu.brand_string)",
+        ),
+    }
+}
+
+/// Check how many CPU temperature sensors of a platform respond
+pub fn check_sensors(responding: usize, total: usize) -> Check {
+    const NAME: &str = "CPU sensors";
+    let detail = format!("{}/{} responding", responding, total);
+    if responding > 0 {
+        Check::pass(NAME, detail)
+    } else {
+        Check::fail(
+            NAME,
+            detail,
+            "add the keys for your machine to sensors.toml",
+        )
+    }
+}
+
+/// Check that the config file exists and parses
+pub fn check_config(path: &Path) -> Check {
+    const NAME: &str = "Config file";
+    if !path.exists() {
+        return Check::fail(
+            NAME,
+            format!("{} does not exist", path.display()),
+            "run `macstats config --example` for a template",
+        );
+    }
+
+    let parsed = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| toml::from_str::<Config>(&content).map_err(|e| e.to_string()));
+    match parsed {
+        Ok(_) => Check::pass(NAME, path.display().to_string()),
+        Err(e) => Check::fail(
+            NAME,
+            format!("invalid: {}", e),
+            "compare with `macstats config --example`",
+        ),
+    }
+}
+
+/// Check the result of an InfluxDB connection test
+pub fn check_influx(result: &Result<(), InfluxError>) -> Check {
+    const NAME: &str = "InfluxDB";
+    match result {
+        Ok(()) => Check::pass(NAME, "reachable"),
+        Err(InfluxError::Http(e)) => Check::fail(
+            NAME,
+            e.to_string(),
+            "check that InfluxDB is running and the url in the config is correct",
+        ),
+        Err(InfluxError::Server { status: 401, .. }) => Check::fail(
+            NAME,
+            "authentication failed",
+            "check the username/password or token in the config",
+        ),
+        Err(e) => Check::fail(
+            NAME,
+            e.to_string(),
+            "check the [influx] section of the config",
+        ),
+    }
+}
+
+/// Number of CPU temperature keys of a platform that produce a reading
+pub fn responding_cpu_keys(source: &mut dyn SensorSource, platform: Platform) -> usize {
+    cpu_temperature_keys(platform)
+        .into_iter()
+        .filter(|key| {
+            macsmc::source::read_temperature(source, key).is_ok_and(|temp| temp.is_plausible())
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macsmc::{keys::SmcKey, SmcData};
+    use std::{collections::HashMap, env};
+
+    struct FakeSource(HashMap<&'static str, SmcData>);
+
+    impl SensorSource for FakeSource {
+        fn read(&mut self, key: &str) -> macsmc::Result<SmcData> {
+            self.0
+                .get(key)
+                .cloned()
+                .ok_or_else(|| SmcError::KeyNotFound(key.to_string()))
+        }
+    }
+
+    /// A source where all `keys` read `celsius`
+    fn source(keys: &[&'static SmcKey], celsius: f32) -> FakeSource {
+        FakeSource(keys.iter().map(|key| (key.key, SmcData::Float(celsius))).collect())
+    }
+
+    #[test]
+    fn smc_connected() {
+        assert!(check_smc(None).passed);
+    }
+
+    #[test]
+    fn smc_errors_get_a_hint() {
+        let check = check_smc(Some(&SmcError::InsufficientPrivileges));
+        assert!(!check.passed);
+        assert_eq!(check.hint, Some("run macstats with sudo"));
+
+        let check = check_smc(Some(&SmcError::NotAvailable));
+        assert!(!check.passed);
+        assert_eq!(check.detail, "AppleSMC service not found");
+
+        let check = check_smc(Some(&SmcError::NotResponding));
+        assert!(!check.passed);
+        assert_eq!(check.hint, Some("the SMC is busy; try again in a moment"));
+    }
+
+    #[test]
+    fn probe_finds_the_platform_with_most_responding_keys() {
+        let mut intel = source(&cpu_temperature_keys(Platform::Intel), 55.0);
+        assert_eq!(probe_platform(&mut intel), Some(Platform::Intel));
+
+        // M2 shares some keys with M1 and M4, but has the most of them
+        let mut m2 = source(&cpu_temperature_keys(Platform::M2), 45.0);
+        assert_eq!(probe_platform(&mut m2), Some(Platform::M2));
+
+        let mut m3 = source(&cpu_temperature_keys(Platform::M3), 45.0);
+        assert_eq!(probe_platform(&mut m3), Some(Platform::M3));
+    }
+
+    #[test]
+    fn probe_ignores_missing_and_implausible_keys() {
+        assert_eq!(probe_platform(&mut FakeSource(HashMap::new())), None);
+
+        let mut implausible = source(&cpu_temperature_keys(Platform::M2), -100.0);
+        assert_eq!(probe_platform(&mut implausible), None);
+    }
+
+    #[test]
+    fn platform_of_the_same_generation_passes() {
+        assert!(check_platform(Some(Platform::M2), Some(Platform::M2)).passed);
+        // Variants share the keys of their generation
+        assert!(check_platform(Some(Platform::M2Pro), Some(Platform::M2)).passed);
+        assert!(check_platform(Some(Platform::Intel), Some(Platform::Intel)).passed);
+    }
+
+    #[test]
+    fn platform_mismatch_fails() {
+        let check = check_platform(Some(Platform::M3), Some(Platform::M2));
+        assert!(!check.passed);
+        assert_eq!(check.detail, "detected M3 but keys match M2");
+
+        assert!(!check_platform(Some(Platform::M2), None).passed);
+        assert!(!check_platform(None, Some(Platform::M2)).passed);
+        assert!(!check_platform(None, None).passed);
+    }
+
+    #[test]
+    fn sensors_pass_when_any_respond() {
+        let check = check_sensors(3, 10);
+        assert!(check.passed);
+        assert_eq!(check.detail, "3/10 responding");
+
+        let check = check_sensors(0, 10);
+        assert!(!check.passed);
+        assert_eq!(check.detail, "0/10 responding");
+    }
+
+    #[test]
+    fn config_file_checks() {
+        let path = env::temp_dir().join(format!("macstats-doctor-{}.toml", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let check = check_config(&path);
+        assert!(!check.passed);
+        assert!(check.detail.ends_with("does not exist"));
+
+        fs::write(&path, toml::to_string(&Config::default()).unwrap()).unwrap();
+        let valid = check_config(&path);
+        fs::write(&path, "interval = \"soon\"").unwrap();
+        let invalid = check_config(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(valid.passed);
+        assert!(!invalid.passed);
+        assert!(invalid.detail.starts_with("invalid: "));
+    }
+
+    #[test]
+    fn influx_checks() {
+        assert!(check_influx(&Ok(())).passed);
+
+        let unauthorized = InfluxError::Server {
+            status: 401,
+            message: "authentication failed".to_string(),
+        };
+        let check = check_influx(&Err(unauthorized));
+        assert!(!check.passed);
+        assert_eq!(check.hint, Some("check the username/password or token in the config"));
+
+        let unreachable = reqwest::Client::new().get("not a url").build().unwrap_err();
+        let check = check_influx(&Err(InfluxError::Http(unreachable)));
+        assert!(!check.passed);
+        assert_eq!(
+            check.hint,
+            Some("check that InfluxDB is running and the url in the config is correct")
+        );
+
+        let check = check_influx(&Err(InfluxError::Config("no org".to_string())));
+        assert!(!check.passed);
+        assert_eq!(check.hint, Some("check the [influx] section of the config"));
+    }
+}
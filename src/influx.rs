@@ -1,8 +1,20 @@
 //! InfluxDB integration for macstats
 
-use reqwest::Client;
+use futures_util::Stream;
+use reqwest::{Body, Client};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, error::Error as StdError, fmt, time::{SystemTime, UNIX_EPOCH}};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    error::Error as StdError,
+    fmt,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 /// InfluxDB configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +29,9 @@ pub struct InfluxConfig {
     pub password: Option<String>,
     /// Optional organization (for InfluxDB v2)
     pub org: Option<String>,
+    /// Optional organization ID (for InfluxDB v2, used instead of `org` when set)
+    #[serde(default)]
+    pub org_id: Option<String>,
     /// Optional token (for InfluxDB v2)
     pub token: Option<String>,
     /// Optional bucket (for InfluxDB v2, defaults to database name)
@@ -25,6 +40,18 @@ pub struct InfluxConfig {
     pub measurement_prefix: Option<String>,
     /// Additional tags to add to all metrics
     pub tags: Option<HashMap<String, String>>,
+    /// Maximum number of points sent in one HTTP write (defaults to 5000)
+    #[serde(default)]
+    pub max_points_per_write: Option<usize>,
+    /// Longest tag value in characters; longer values are truncated (no limit if unset)
+    #[serde(default)]
+    pub max_tag_value_len: Option<usize>,
+    /// Additional endpoints that receive the same data
+    ///
+    /// Only their connection and authentication settings are used; the data
+    /// is formatted with this configuration's prefix and tags.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secondary: Vec<InfluxConfig>,
 }
 
 impl Default for InfluxConfig {
@@ -35,10 +62,14 @@ impl Default for InfluxConfig {
             username: None,
             password: None,
             org: None,
+            org_id: None,
             token: None,
             bucket: None,
             measurement_prefix: Some("mac".to_string()),
             tags: None,
+            max_points_per_write: None,
+            max_tag_value_len: None,
+            secondary: Vec::new(),
         }
     }
 }
@@ -47,6 +78,7 @@ impl Default for InfluxConfig {
 pub struct InfluxClient {
     config: InfluxConfig,
     client: Client,
+    default_tags: HashMap<String, String>,
 }
 
 /// InfluxDB error
@@ -83,7 +115,7 @@ impl From<reqwest::Error> for InfluxError {
 type Result<T> = std::result::Result<T, InfluxError>;
 
 /// Metric data point
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Metric {
     /// Measurement name
     pub measurement: String,
@@ -126,39 +158,117 @@ impl InfluxClient {
     /// Create a new InfluxDB client
     pub fn new(config: InfluxConfig) -> Result<Self> {
         let client = Client::new();
-        Ok(Self { config, client })
+        Ok(Self {
+            config,
+            client,
+            default_tags: HashMap::new(),
+        })
+    }
+
+    /// Add a tag to all metrics that is overridden by configured or metric tags
+    pub fn default_tag(mut self, key: &str, value: &str) -> Self {
+        self.default_tags.insert(key.to_string(), value.to_string());
+        self
     }
 
     /// Write metrics to InfluxDB
-    pub async fn write_metrics(&self, metrics: Vec<Metric>) -> Result<()> {
-        if metrics.is_empty() {
-            return Ok(());
+    ///
+    /// Metrics are split into writes of at most `max_points_per_write` points.
+    /// With secondary endpoints configured, a write succeeds if any endpoint
+    /// accepts it; failed endpoints are reported on stderr.
+    pub async fn write_metrics(&self, mut metrics: Vec<Metric>) -> Result<()> {
+        let max_points = self
+            .config
+            .max_points_per_write
+            .unwrap_or(DEFAULT_MAX_POINTS_PER_WRITE);
+
+        // Stamp once up front, so streamed chunks formatted later agree on "now"
+        let now = current_time()?;
+        for metric in &mut metrics {
+            metric.timestamp.get_or_insert(now);
         }
 
-        let line_protocol = self.format_line_protocol(&metrics)?;
-        
-        // Determine API version and build request
-        if self.config.token.is_some() {
-            self.write_v2(&line_protocol).await
+        let metrics = Arc::new(metrics);
+        for batch in batch_ranges(metrics.len(), max_points) {
+            self.write_batch(&metrics, batch).await?;
+        }
+        Ok(())
+    }
+
+    /// Write one batch of metrics to all endpoints
+    async fn write_batch(&self, metrics: &Arc<Vec<Metric>>, batch: Range<usize>) -> Result<()> {
+        if self.config.secondary.is_empty() {
+            return self.write_to(&self.config, self.body(metrics, batch)?).await;
+        }
+
+        let mut results = Vec::with_capacity(1 + self.config.secondary.len());
+        for endpoint in std::iter::once(&self.config).chain(&self.config.secondary) {
+            let result = self.write_to(endpoint, self.body(metrics, batch.clone())?).await;
+            if let Err(e) = &result {
+                eprintln!("InfluxDB write to {} failed: {}", endpoint.url, e);
+            }
+            results.push(result);
+        }
+        any_succeeded(results)
+    }
+
+    /// Request body for a batch
+    ///
+    /// Small batches are formatted in one go. Larger ones are streamed in
+    /// chunks of `STREAM_CHUNK_POINTS`, so the full line protocol is never
+    /// held in memory at once.
+    fn body(&self, metrics: &Arc<Vec<Metric>>, batch: Range<usize>) -> Result<Body> {
+        if batch.len() <= STREAM_THRESHOLD_POINTS {
+            return Ok(Body::from(self.line_protocol(&metrics[batch])?));
+        }
+        Ok(Body::wrap_stream(self.line_protocol_stream(metrics, batch)))
+    }
+
+    /// Line protocol for a batch as a stream of chunks that are formatted as they are sent
+    fn line_protocol_stream(
+        &self,
+        metrics: &Arc<Vec<Metric>>,
+        batch: Range<usize>,
+    ) -> impl Stream<Item = Result<String>> {
+        let config = self.config.clone();
+        let default_tags = self.default_tags.clone();
+        let metrics = Arc::clone(metrics);
+        let end = batch.end;
+        let chunks = batch.step_by(STREAM_CHUNK_POINTS).map(move |start| {
+            let chunk_end = (start + STREAM_CHUNK_POINTS).min(end);
+            let chunk = &metrics[start..chunk_end];
+            let mut lines = format_line_protocol(&config, &default_tags, chunk)?;
+            if chunk_end < end {
+                lines.push('\n');
+            }
+            Ok(lines)
+        });
+        futures_util::stream::iter(chunks)
+    }
+
+    /// Write line protocol to one endpoint, picking the API version by its auth settings
+    async fn write_to(&self, endpoint: &InfluxConfig, body: Body) -> Result<()> {
+        if endpoint.token.is_some() {
+            self.write_v2(endpoint, body).await
         } else {
-            self.write_v1(&line_protocol).await
+            self.write_v1(endpoint, body).await
         }
     }
 
     /// Write using InfluxDB v1 API
-    async fn write_v1(&self, data: &str) -> Result<()> {
-        let mut url = format!("{}/write?db={}", self.config.url, self.config.database);
+    async fn write_v1(&self, endpoint: &InfluxConfig, body: Body) -> Result<()> {
+        let mut url = format!("{}/write?db={}", endpoint.url, endpoint.database);
         
         let mut request = self.client.post(&url);
 
         // Add authentication if provided
-        if let (Some(username), Some(password)) = (&self.config.username, &self.config.password) {
+        if let (Some(username), Some(password)) = (&endpoint.username, &endpoint.password) {
             request = request.basic_auth(username, Some(password));
         }
 
         let response = request
             .header("Content-Type", "application/octet-stream")
-            .body(data.to_string())
+            .body(body)
             .send()
             .await?;
 
@@ -166,27 +276,25 @@ impl InfluxClient {
     }
 
     /// Write using InfluxDB v2 API
-    async fn write_v2(&self, data: &str) -> Result<()> {
-        let org = self.config.org.as_ref().ok_or_else(|| {
-            InfluxError::Config("Organization required for InfluxDB v2".to_string())
-        })?;
+    async fn write_v2(&self, endpoint: &InfluxConfig, body: Body) -> Result<()> {
+        let org_param = org_param(endpoint)?;
         
-        let bucket = self.config.bucket.as_ref().unwrap_or(&self.config.database);
+        let bucket = endpoint.bucket.as_ref().unwrap_or(&endpoint.database);
         
-        let token = self.config.token.as_ref().ok_or_else(|| {
+        let token = endpoint.token.as_ref().ok_or_else(|| {
             InfluxError::Config("Token required for InfluxDB v2".to_string())
         })?;
 
         let url = format!(
-            "{}/api/v2/write?org={}&bucket={}",
-            self.config.url, org, bucket
+            "{}/api/v2/write?{}&bucket={}",
+            endpoint.url, org_param, bucket
         );
 
         let response = self.client
             .post(&url)
             .header("Authorization", format!("Token {}", token))
             .header("Content-Type", "text/plain; charset=utf-8")
-            .body(data.to_string())
+            .body(body)
             .send()
             .await?;
 
@@ -199,68 +307,17 @@ impl InfluxClient {
         if status.is_success() {
             Ok(())
         } else {
-            let message = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            let body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
             Err(InfluxError::Server {
                 status: status.as_u16(),
-                message,
+                message: server_error_message(status.as_u16(), &body),
             })
         }
     }
 
-    /// Format metrics as InfluxDB line protocol
-    fn format_line_protocol(&self, metrics: &[Metric]) -> Result<String> {
-        let current_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| InfluxError::Config(format!("Time error: {}", e)))?
-            .as_nanos() as u64;
-
-        let mut lines = Vec::new();
-
-        for metric in metrics {
-            let measurement = if let Some(prefix) = &self.config.measurement_prefix {
-                format!("{}_{}", prefix, metric.measurement)
-            } else {
-                metric.measurement.clone()
-            };
-
-            // Build tags
-            let mut all_tags = HashMap::new();
-            
-            // Add global tags
-            if let Some(global_tags) = &self.config.tags {
-                all_tags.extend(global_tags.clone());
-            }
-            
-            // Add metric-specific tags
-            all_tags.extend(metric.tags.clone());
-
-            // Format measurement and tags
-            let mut line = measurement;
-            if !all_tags.is_empty() {
-                let tag_string: Vec<String> = all_tags
-                    .iter()
-                    .map(|(k, v)| format!("{}={}", escape_tag_key(k), escape_tag_value(v)))
-                    .collect();
-                line.push(',');
-                line.push_str(&tag_string.join(","));
-            }
-
-            // Add field
-            line.push(' ');
-            line.push_str(&format!("{}={}", 
-                escape_field_key(&metric.field), 
-                format_field_value(metric.value)
-            ));
-
-            // Add timestamp
-            let timestamp = metric.timestamp.unwrap_or(current_time);
-            line.push(' ');
-            line.push_str(&timestamp.to_string());
-
-            lines.push(line);
-        }
-
-        Ok(lines.join("\n"))
+    /// Format metrics as InfluxDB line protocol with this client's settings
+    pub fn line_protocol(&self, metrics: &[Metric]) -> Result<String> {
+        format_line_protocol(&self.config, &self.default_tags, metrics)
     }
 
     /// Test connection to InfluxDB
@@ -292,11 +349,187 @@ impl InfluxClient {
     }
 }
 
+/// Format metrics as InfluxDB line protocol
+///
+/// `default_tags` are overridden by the configured tags, which are in turn
+/// overridden by the tags of each metric. Metrics without a timestamp get the
+/// current time.
+pub fn format_line_protocol(
+    config: &InfluxConfig,
+    default_tags: &HashMap<String, String>,
+    metrics: &[Metric],
+) -> Result<String> {
+    let current_time = current_time()?;
+
+    let mut lines = Vec::new();
+
+    for metric in metrics {
+        let measurement = if let Some(prefix) = &config.measurement_prefix {
+            format!("{}_{}", prefix, metric.measurement)
+        } else {
+            metric.measurement.clone()
+        };
+
+        // Build tags
+        let mut all_tags = default_tags.clone();
+
+        // Add global tags
+        if let Some(global_tags) = &config.tags {
+            all_tags.extend(global_tags.clone());
+        }
+        
+        // Add metric-specific tags
+        all_tags.extend(metric.tags.clone());
+
+        // Format measurement and tags
+        let mut line = measurement;
+        if !all_tags.is_empty() {
+            let tag_string: Vec<String> = all_tags
+                .iter()
+                .map(|(k, v)| {
+                    let v = check_tag_value(k, v, config.max_tag_value_len);
+                    format!("{}={}", escape_tag_key(k), escape_tag_value(&v))
+                })
+                .collect();
+            line.push(',');
+            line.push_str(&tag_string.join(","));
+        }
+
+        // Add field
+        line.push(' ');
+        line.push_str(&format!("{}={}", 
+            escape_field_key(&metric.field), 
+            format_field_value(metric.value)
+        ));
+
+        // Add timestamp
+        let timestamp = metric.timestamp.unwrap_or(current_time);
+        line.push(' ');
+        line.push_str(&timestamp.to_string());
+
+        lines.push(line);
+    }
+
+    Ok(lines.join("\n"))
+}
+
+/// Points per write when `max_points_per_write` is not set
+const DEFAULT_MAX_POINTS_PER_WRITE: usize = 5000;
+
+/// Batches with more points than this are streamed instead of formatted at once
+const STREAM_THRESHOLD_POINTS: usize = 1000;
+
+/// Points formatted per chunk of a streamed request body
+const STREAM_CHUNK_POINTS: usize = 250;
+
+/// Index ranges of the writes for `len` metrics, at most `max_points` (but at least one) each
+fn batch_ranges(len: usize, max_points: usize) -> impl Iterator<Item = Range<usize>> {
+    let max_points = max_points.max(1);
+    (0..len)
+        .step_by(max_points)
+        .map(move |start| start..(start + max_points).min(len))
+}
+
+/// Query parameter naming the organization of a v2 write, preferring its ID
+fn org_param(endpoint: &InfluxConfig) -> Result<String> {
+    match (&endpoint.org_id, &endpoint.org) {
+        (Some(org_id), _) => Ok(format!("orgID={}", org_id)),
+        (None, Some(org)) => Ok(format!("org={}", org)),
+        (None, None) => Err(InfluxError::Config(
+            "Organization required for InfluxDB v2".to_string(),
+        )),
+    }
+}
+
+/// Combined result of writing to several endpoints: fine if any of them took
+/// the data, otherwise the first endpoint's error
+fn any_succeeded(results: Vec<Result<()>>) -> Result<()> {
+    let mut first_error = None;
+    for result in results {
+        match result {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+/// Current time in nanoseconds since the epoch
+fn current_time() -> Result<u64> {
+    Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| InfluxError::Config(format!("Time error: {}", e)))?
+        .as_nanos() as u64)
+}
+
+/// Maximum number of characters of a server error body kept in the error
+const MAX_ERROR_BODY_CHARS: usize = 512;
+
+/// Build a concise error message from an error response
+fn server_error_message(status: u16, body: &str) -> String {
+    let hint = match status {
+        400 => Some("malformed line protocol"),
+        401 => Some("authentication failed"),
+        404 => Some("database/bucket not found"),
+        _ => None,
+    };
+
+    let body = body.trim();
+    let body = match body.char_indices().nth(MAX_ERROR_BODY_CHARS) {
+        Some((end, _)) => format!("{}... (truncated)", &body[..end]),
+        None => body.to_string(),
+    };
+
+    match hint {
+        Some(hint) if body.is_empty() => hint.to_string(),
+        Some(hint) => format!("{}: {}", hint, body),
+        None => body,
+    }
+}
+
 // Helper functions for InfluxDB line protocol escaping
 fn escape_tag_key(s: &str) -> String {
     s.replace(',', "\\,").replace(' ', "\\ ").replace('=', "\\=")
 }
 
+// Each problem is reported once per run rather than on every write
+static TRUNCATION_WARNED: AtomicBool = AtomicBool::new(false);
+static CARDINALITY_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Truncate a tag value to `max_len` characters, warning about values that
+/// would create a new series on every write
+fn check_tag_value<'a>(key: &str, value: &'a str, max_len: Option<usize>) -> Cow<'a, str> {
+    if looks_high_cardinality(value) && !CARDINALITY_WARNED.swap(true, Ordering::Relaxed) {
+        eprintln!(
+            "Warning: tag {}={} looks like it changes on every write (e.g. a timestamp); \
+             each value creates a new series in InfluxDB",
+            key, value
+        );
+    }
+
+    match max_len {
+        Some(max_len) if value.chars().count() > max_len => {
+            if !TRUNCATION_WARNED.swap(true, Ordering::Relaxed) {
+                eprintln!(
+                    "Warning: tag {} is longer than max_tag_value_len ({}) and was truncated",
+                    key, max_len
+                );
+            }
+            Cow::Owned(value.chars().take(max_len).collect())
+        }
+        _ => Cow::Borrowed(value),
+    }
+}
+
+// Ten or more digits in a row, like a Unix timestamp or a counter
+fn looks_high_cardinality(value: &str) -> bool {
+    value
+        .split(|c: char| !c.is_ascii_digit())
+        .any(|digits| digits.len() >= 10)
+}
+
 fn escape_tag_value(s: &str) -> String {
     s.replace(',', "\\,").replace(' ', "\\ ").replace('=', "\\=")
 }
@@ -311,4 +544,213 @@ fn format_field_value(value: f64) -> String {
     } else {
         value.to_string()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches_are_capped() {
+        let batches: Vec<Range<usize>> = batch_ranges(250, 100).collect();
+        assert_eq!(batches, [0..100, 100..200, 200..250]);
+        let batches: Vec<Range<usize>> = batch_ranges(200, 100).collect();
+        assert_eq!(batches, [0..100, 100..200]);
+    }
+
+    #[test]
+    fn cap_of_zero_writes_one_point_at_a_time() {
+        let batches: Vec<Range<usize>> = batch_ranges(3, 0).collect();
+        assert_eq!(batches, [0..1, 1..2, 2..3]);
+    }
+
+    #[test]
+    fn no_metrics_no_writes() {
+        assert_eq!(batch_ranges(0, 100).count(), 0);
+    }
+
+    fn metrics(count: usize) -> Vec<Metric> {
+        (0..count)
+            .map(|i| {
+                Metric::new("temperature", "value", i as f64 / 4.0)
+                    .tag("sensor", &format!("Core {}", i % 8))
+                    .timestamp(1_700_000_000_000_000_000 + i as u64)
+            })
+            .collect()
+    }
+
+    async fn streamed(
+        client: &InfluxClient,
+        metrics: &Arc<Vec<Metric>>,
+        batch: Range<usize>,
+    ) -> String {
+        use futures_util::TryStreamExt;
+
+        let chunks: Vec<String> = client
+            .line_protocol_stream(metrics, batch)
+            .try_collect()
+            .await
+            .unwrap();
+        chunks.concat()
+    }
+
+    #[tokio::test]
+    async fn streamed_body_matches_line_protocol() {
+        let client = InfluxClient::new(InfluxConfig::default())
+            .unwrap()
+            .default_tag("host", "test");
+        let count = STREAM_THRESHOLD_POINTS + STREAM_CHUNK_POINTS / 2 + 1;
+        let metrics = Arc::new(metrics(count));
+
+        let expected = client.line_protocol(&metrics).unwrap();
+        assert_eq!(streamed(&client, &metrics, 0..count).await, expected);
+
+        // A later batch of the same metrics, not starting on a chunk boundary
+        let batch = 7..count;
+        let expected = client.line_protocol(&metrics[batch.clone()]).unwrap();
+        assert_eq!(streamed(&client, &metrics, batch).await, expected);
+    }
+
+    fn org_config(org_id: Option<&str>, org: Option<&str>) -> InfluxConfig {
+        InfluxConfig {
+            org_id: org_id.map(String::from),
+            org: org.map(String::from),
+            ..InfluxConfig::default()
+        }
+    }
+
+    #[test]
+    fn org_id_is_preferred_over_org_name() {
+        let param = org_param(&org_config(Some("0123abcd"), Some("home"))).unwrap();
+        assert_eq!(param, "orgID=0123abcd");
+        let param = org_param(&org_config(Some("0123abcd"), None)).unwrap();
+        assert_eq!(param, "orgID=0123abcd");
+    }
+
+    #[test]
+    fn org_name_is_used_without_an_id() {
+        assert_eq!(org_param(&org_config(None, Some("home"))).unwrap(), "org=home");
+    }
+
+    #[test]
+    fn v2_writes_need_an_organization() {
+        let result = org_param(&org_config(None, None));
+        assert!(matches!(result, Err(InfluxError::Config(_))));
+    }
+
+    fn config_error(message: &str) -> Result<()> {
+        Err(InfluxError::Config(message.to_string()))
+    }
+
+    #[test]
+    fn one_successful_endpoint_is_enough() {
+        assert!(any_succeeded(vec![config_error("primary"), Ok(())]).is_ok());
+        assert!(any_succeeded(vec![Ok(()), config_error("secondary")]).is_ok());
+    }
+
+    #[test]
+    fn first_error_wins_when_all_endpoints_fail() {
+        let result = any_succeeded(vec![config_error("primary"), config_error("secondary")]);
+        assert!(matches!(result, Err(InfluxError::Config(m)) if m == "primary"));
+    }
+
+    /// Whether `request` holds complete headers and as much body as they announce
+    fn request_complete(request: &[u8]) -> bool {
+        let request = String::from_utf8_lossy(request);
+        let header_end = match request.find("\r\n\r\n") {
+            Some(end) => end + 4,
+            None => return false,
+        };
+        let content_length = request[..header_end]
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .map_or(0, |(_, value)| value.trim().parse().unwrap());
+        request.len() >= header_end + content_length
+    }
+
+    /// Answer one HTTP request on a local port with `status`, returning the URL to send it to
+    async fn serve_once(status: &'static str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            // Read the whole request first, so answering doesn't reset the connection
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            while !request_complete(&request) {
+                match stream.read(&mut buf).await.unwrap() {
+                    0 => break,
+                    n => request.extend_from_slice(&buf[..n]),
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+        url
+    }
+
+    fn client_for(primary: String, secondary: String) -> InfluxClient {
+        let config = InfluxConfig {
+            url: primary,
+            secondary: vec![InfluxConfig {
+                url: secondary,
+                ..InfluxConfig::default()
+            }],
+            ..InfluxConfig::default()
+        };
+        InfluxClient::new(config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn write_succeeds_when_only_the_secondary_takes_it() {
+        let primary = serve_once("500 Internal Server Error").await;
+        let secondary = serve_once("204 No Content").await;
+        let client = client_for(primary, secondary);
+        assert!(client.write_metrics(metrics(3)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn write_fails_with_the_primary_error_when_all_endpoints_fail() {
+        let primary = serve_once("500 Internal Server Error").await;
+        let secondary = serve_once("503 Service Unavailable").await;
+        let client = client_for(primary, secondary);
+        let result = client.write_metrics(metrics(3)).await;
+        assert!(matches!(result, Err(InfluxError::Server { status: 500, .. })));
+    }
+
+    #[test]
+    fn error_message_hints_at_status() {
+        assert_eq!(
+            server_error_message(401, "{\"error\":\"unauthorized\"}\n"),
+            "authentication failed: {\"error\":\"unauthorized\"}"
+        );
+        assert_eq!(server_error_message(500, "internal error"), "internal error");
+    }
+
+    #[test]
+    fn error_message_with_empty_body() {
+        assert_eq!(server_error_message(404, "  \n"), "database/bucket not found");
+        assert_eq!(server_error_message(503, ""), "");
+    }
+
+    #[test]
+    fn long_error_body_is_truncated_on_a_char_boundary() {
+        // Multi-byte characters, so a byte-indexed cut would split one
+        let body = "é".repeat(MAX_ERROR_BODY_CHARS + 10);
+        let message = server_error_message(400, &body);
+        let expected = format!(
+            "malformed line protocol: {}... (truncated)",
+            "é".repeat(MAX_ERROR_BODY_CHARS)
+        );
+        assert_eq!(message, expected);
+
+        let body = "x".repeat(MAX_ERROR_BODY_CHARS);
+        assert_eq!(server_error_message(500, &body), body);
+    }
+}
@@ -3,16 +3,28 @@
 //! Supports both console output and InfluxDB logging with configurable credentials.
 
 mod config;
+mod csv;
+mod derived;
+mod doctor;
 mod influx;
+mod prometheus;
+mod sensors;
+mod state;
 
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
 use config::{Config, MetricsConfig};
+use csv::CsvWriter;
 use influx::{InfluxClient, Metric};
-use macsmc::{keys::*, SmcClient, SmcError};
+use macsmc::{
+    keys::*, platform, FanMode, Platform, Rpm, SensorSource, SmcClient, SmcError,
+};
+use state::StateFile;
 use std::{
     collections::HashMap,
     error::Error as StdError,
     fmt::{self, Display},
+    ops::Deref,
+    path::PathBuf,
     time::{SystemTime, UNIX_EPOCH},
 };
 
@@ -23,6 +35,7 @@ enum Error {
     Smc(SmcError),
     Influx(influx::InfluxError),
     Config(Box<dyn StdError>),
+    Io(std::io::Error),
     UnknownCommand(String),
 }
 
@@ -32,6 +45,7 @@ impl StdError for Error {
             Error::Smc(e) => Some(e),
             Error::Influx(e) => Some(e),
             Error::Config(e) => Some(e.as_ref()),
+            Error::Io(e) => Some(e),
             Error::UnknownCommand(_) => None,
         }
     }
@@ -43,6 +57,7 @@ impl Display for Error {
             Error::Smc(e) => write!(f, "SMC Error: {}", e),
             Error::Influx(e) => write!(f, "InfluxDB Error: {}", e),
             Error::Config(e) => write!(f, "Config Error: {}", e),
+            Error::Io(e) => write!(f, "IO Error: {}", e),
             Error::UnknownCommand(cmd) => write!(f, "Unknown command: {}", cmd),
         }
     }
@@ -60,6 +75,12 @@ impl From<influx::InfluxError> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
 impl From<Box<dyn StdError>> for Error {
     fn from(e: Box<dyn StdError>) -> Self {
         Error::Config(e)
@@ -75,18 +96,42 @@ struct Cli {
     command: Option<Commands>,
 }
 
+#[derive(Args, Clone, Copy)]
+struct DisplayArgs {
+    /// Order readings by name, value (descending) or SMC key
+    #[arg(long, value_enum)]
+    sort: Option<SortBy>,
+    /// Print a table or InfluxDB line protocol (e.g. for `influx write`)
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum Format {
+    Table,
+    InfluxLp,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum SortBy {
+    Name,
+    Value,
+    Key,
+}
+
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// Display CPU information
-    Cpu,
+    Cpu(DisplayArgs),
     /// Display GPU information
-    Gpu,
+    Gpu(DisplayArgs),
     /// Display system information
-    System,
+    System(DisplayArgs),
     /// Display power information
-    Power,
+    Power(DisplayArgs),
     /// Display all information
-    All,
+    All(DisplayArgs),
     /// Send metrics to InfluxDB
     Influx {
         /// InfluxDB URL
@@ -104,6 +149,9 @@ enum Commands {
         /// Organization (for v2)
         #[arg(long)]
         org: Option<String>,
+        /// Organization ID (for v2, used instead of --org)
+        #[arg(long)]
+        org_id: Option<String>,
         /// Token (for v2)
         #[arg(long)]
         token: Option<String>,
@@ -116,10 +164,20 @@ enum Commands {
         /// Additional tags (format: key=value,key2=value2)
         #[arg(long)]
         tags: Option<String>,
+        /// Maximum number of points per HTTP write
+        #[arg(long)]
+        max_points: Option<usize>,
         /// Test connection only
         #[arg(long)]
         test: bool,
     },
+    /// Print metrics in the Prometheus text format
+    Prometheus {
+        /// Write to this file instead of stdout, replacing it atomically
+        /// (for node_exporter's textfile collector)
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
     /// Configuration management
     Config {
         /// Show current configuration
@@ -137,6 +195,41 @@ enum Commands {
         /// Collection interval in seconds
         #[arg(short, long)]
         interval: Option<u64>,
+        /// Also append each interval's metrics to this CSV file
+        #[arg(long)]
+        csv: Option<PathBuf>,
+        /// Print each interval's metrics as a JSON line (InfluxDB becomes optional)
+        #[arg(long)]
+        stdout_json: bool,
+        /// Keep the min/max of every metric in this JSON file across runs
+        #[arg(long)]
+        state_file: Option<PathBuf>,
+    },
+    /// Diagnose common setup problems
+    Doctor,
+    /// Set a fan's mode or target speed (requires root)
+    ///
+    /// A forced fan is held until Ctrl+C and then handed back to the SMC.
+    #[command(group(
+        ArgGroup::new("setting")
+            .required(true)
+            .args(["mode", "rpm", "percent", "auto"])
+    ))]
+    Fan {
+        /// Fan index
+        index: u8,
+        /// Control mode (auto or forced)
+        #[arg(long)]
+        mode: Option<FanMode>,
+        /// Target speed in RPM
+        #[arg(long)]
+        rpm: Option<u32>,
+        /// Target speed in percent of the fan's speed range
+        #[arg(long)]
+        percent: Option<f32>,
+        /// Hand the fan back to the SMC (same as --mode auto)
+        #[arg(long)]
+        auto: bool,
     },
 }
 
@@ -151,26 +244,29 @@ async fn main() {
 }
 
 async fn run(cli: Cli) -> Result<()> {
+    if let Err(e) = sensors::load_overrides() {
+        eprintln!("Error loading sensor overrides: {}", e);
+    }
+
     match cli.command {
-        Some(Commands::Cpu) => {
-            let mut client = macsmc::connect()?;
-            print_cpu_info(&mut client)?;
+        Some(Commands::Cpu(args)) => {
+            let metrics = MetricsConfig { cpu_temp: true, ..MetricsConfig::none() };
+            display(args, &metrics, print_cpu_info)?;
         }
-        Some(Commands::Gpu) => {
-            let mut client = macsmc::connect()?;
-            print_gpu_info(&mut client)?;
+        Some(Commands::Gpu(args)) => {
+            let metrics = MetricsConfig { gpu_temp: true, ..MetricsConfig::none() };
+            display(args, &metrics, print_gpu_info)?;
         }
-        Some(Commands::System) => {
-            let mut client = macsmc::connect()?;
-            print_system_info(&mut client)?;
+        Some(Commands::System(args)) => {
+            let metrics = MetricsConfig { system_temp: true, ..MetricsConfig::none() };
+            display(args, &metrics, print_system_info)?;
         }
-        Some(Commands::Power) => {
-            let mut client = macsmc::connect()?;
-            print_power_info(&mut client)?;
+        Some(Commands::Power(args)) => {
+            let metrics = MetricsConfig { power: true, ..MetricsConfig::none() };
+            display(args, &metrics, print_power_info)?;
         }
-        Some(Commands::All) => {
-            let mut client = macsmc::connect()?;
-            print_all_info(&mut client)?;
+        Some(Commands::All(args)) => {
+            display(args, &MetricsConfig::default(), print_all_info)?;
         }
         Some(Commands::Influx {
             url,
@@ -178,10 +274,12 @@ async fn run(cli: Cli) -> Result<()> {
             username,
             password,
             org,
+            org_id,
             token,
             bucket,
             prefix,
             tags,
+            max_points,
             test,
         }) => {
             let mut influx_config = influx::InfluxConfig {
@@ -190,13 +288,17 @@ async fn run(cli: Cli) -> Result<()> {
                 username,
                 password,
                 org,
+                org_id,
                 token,
                 bucket,
                 measurement_prefix: Some(prefix),
                 tags: parse_tags(tags)?,
+                max_points_per_write: max_points,
+                max_tag_value_len: None,
+                secondary: Vec::new(),
             };
 
-            let client = InfluxClient::new(influx_config)?;
+            let client = with_system_tags(InfluxClient::new(influx_config)?);
 
             if test {
                 println!("Testing InfluxDB connection...");
@@ -207,6 +309,14 @@ async fn run(cli: Cli) -> Result<()> {
                 println!("✓ Metrics sent to InfluxDB");
             }
         }
+        Some(Commands::Prometheus { output }) => {
+            let metrics = read_metrics(&MetricsConfig::default())?;
+            let exposition = prometheus::format_exposition(&metrics, &system_tags());
+            match output {
+                Some(path) => prometheus::write_atomic(&path, &exposition)?,
+                None => print!("{}", exposition),
+            }
+        }
         Some(Commands::Config { show, example, path }) => {
             if show {
                 match Config::load() {
@@ -229,36 +339,141 @@ async fn run(cli: Cli) -> Result<()> {
                 println!("Use --show, --example, or --path");
             }
         }
-        Some(Commands::Monitor { interval }) => {
+        Some(Commands::Monitor {
+            interval,
+            csv,
+            stdout_json,
+            state_file,
+        }) => {
             let config = Config::load()?;
-            println!("Loaded configuration: {:?}", config);
             let interval = interval.unwrap_or(config.interval.unwrap_or(30));
             
-            println!("Starting monitoring every {} seconds...", interval);
-            println!("Press Ctrl+C to stop");
+            // Keep stdout clean for the JSON lines
+            if stdout_json {
+                eprintln!("Starting monitoring every {} seconds...", interval);
+                eprintln!("Press Ctrl+C to stop");
+            } else {
+                println!("Starting monitoring every {} seconds...", interval);
+                println!("Press Ctrl+C to stop");
+            }
+
+            let influx_client = match config.influx {
+                Some(influx) => Some(with_system_tags(InfluxClient::new(influx)?)),
+                None if stdout_json => None,
+                None => {
+                    return Err(Error::Config(
+                        "No [influx] section in config; add one or use --stdout-json".into(),
+                    ))
+                }
+            };
+            let mut csv = csv.map(|path| CsvWriter::open(&path)).transpose()?;
+            let mut state = state_file.map(|path| StateFile::open(&path)).transpose()?;
+            let mut smc = None;
+
+            // Tick on a fixed cadence so collection time doesn't skew timestamps
+            let mut ticker = tokio::time::interval(tokio::time::Duration::from_secs(interval));
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
-            let influx_client = InfluxClient::new(config.influx)?;
-            
             loop {
-                if let Err(e) = send_to_influx_with_config(&influx_client, &config.metrics).await {
-                    eprintln!("Error sending metrics: {}", e);
+                ticker.tick().await;
+
+                let cycle = monitor_cycle(
+                    &mut smc,
+                    influx_client.as_ref(),
+                    &config.metrics,
+                    csv.as_mut(),
+                    state.as_mut(),
+                    stdout_json,
+                );
+                match cycle.await {
+                    Ok(()) => {}
+                    // No point in retrying if the SMC can't be used at all
+                    Err(Error::Smc(e)) if !e.is_transient() => return Err(Error::Smc(e)),
+                    Err(e) => eprintln!("Error sending metrics: {}", e),
                 }
-                
-                tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
             }
         }
+        Some(Commands::Doctor) => {
+            let checks = doctor::diagnose().await;
+            for check in &checks {
+                println!("{}", check);
+            }
+
+            let failed = checks.iter().filter(|check| !check.passed).count();
+            if failed == 0 {
+                println!("\nAll checks passed");
+            } else {
+                println!("\n{} check(s) failed", failed);
+            }
+        }
+        Some(Commands::Fan {
+            index,
+            mode,
+            rpm,
+            percent,
+            auto,
+        }) => {
+            let mode = if auto { Some(FanMode::Auto) } else { mode };
+            control_fan(index, mode, rpm, percent).await?;
+        }
         None => {
             // Default behavior - show CPU and power
             let mut client = macsmc::connect()?;
-            print_cpu_info(&mut client)?;
+            print_cpu_info(&mut client, None)?;
             println!();
-            print_power_info(&mut client)?;
+            print_power_info(&mut client, None)?;
         }
     }
 
     Ok(())
 }
 
+/// Apply a fan setting; a forced fan is held until Ctrl+C, then handed back to the SMC
+async fn control_fan(
+    index: u8,
+    mode: Option<FanMode>,
+    rpm: Option<u32>,
+    percent: Option<f32>,
+) -> Result<()> {
+    require_root(unsafe { libc::geteuid() })?;
+
+    let mut client = macsmc::connect()?;
+    if mode == Some(FanMode::Auto) {
+        client.set_fan_mode(index, FanMode::Auto)?;
+        println!("Fan {} is under automatic control", index);
+        return Ok(());
+    }
+
+    let forced = client
+        .set_fan_mode(index, FanMode::Forced)
+        .and_then(|()| match (rpm, percent) {
+            (Some(rpm), _) => client.set_fan_target(index, Rpm(rpm as f32)),
+            (_, Some(percent)) => client.set_fan_target_percentage(index, percent),
+            _ => Ok(()),
+        });
+    if let Err(e) = forced {
+        // Don't leave the fan forced to a stale target
+        let _ = client.set_fan_mode(index, FanMode::Auto);
+        return Err(e.into());
+    }
+
+    println!("Fan {} is under manual control", index);
+    println!("Press Ctrl+C to hand it back to the SMC");
+    tokio::signal::ctrl_c().await?;
+
+    client.set_fan_mode(index, FanMode::Auto)?;
+    println!("Fan {} is under automatic control", index);
+    Ok(())
+}
+
+// Fail with a clear message rather than a privilege error halfway through
+fn require_root(euid: libc::uid_t) -> Result<()> {
+    if euid != 0 {
+        return Err(Error::Config("fan control needs root, run with sudo".into()));
+    }
+    Ok(())
+}
+
 fn parse_tags(tags: Option<String>) -> Result<Option<HashMap<String, String>>> {
     if let Some(tags_str) = tags {
         let mut tag_map = HashMap::new();
@@ -276,13 +491,136 @@ fn parse_tags(tags: Option<String>) -> Result<Option<HashMap<String, String>>> {
     }
 }
 
+/// Tags with the detected chip, macOS version and model identifier
+fn system_tags() -> HashMap<String, String> {
+    let mut tags = HashMap::new();
+    if let Some(platform) = platform::detect_platform() {
+        tags.insert("chip".to_string(), platform.to_string());
+    }
+    if let Some(version) = platform::os_version() {
+        tags.insert("os_version".to_string(), version);
+    }
+    if let Some(model) = platform::model() {
+        tags.insert("model".to_string(), model);
+    }
+    tags
+}
+
+/// Tag all metrics with the detected chip, macOS version and model identifier
+fn with_system_tags(client: InfluxClient) -> InfluxClient {
+    system_tags()
+        .into_iter()
+        .fold(client, |client, (key, value)| client.default_tag(&key, &value))
+}
+
+/// Print readings as a table, or the matching metrics as line protocol
+fn display(
+    args: DisplayArgs,
+    metrics: &MetricsConfig,
+    print_table: fn(&mut SmcClient, Option<SortBy>) -> Result<()>,
+) -> Result<()> {
+    match args.format {
+        Format::Table => {
+            let mut client = macsmc::connect()?;
+            print_table(&mut client, args.sort)
+        }
+        Format::InfluxLp => {
+            let metrics = read_metrics(metrics)?;
+            let lines = influx::format_line_protocol(
+                &influx::InfluxConfig::default(),
+                &system_tags(),
+                &metrics,
+            )?;
+            if !lines.is_empty() {
+                println!("{}", lines);
+            }
+            Ok(())
+        }
+    }
+}
+
 async fn send_to_influx(client: InfluxClient) -> Result<()> {
     let config = MetricsConfig::default();
     send_to_influx_with_config(&client, &config).await
 }
 
 async fn send_to_influx_with_config(client: &InfluxClient, config: &MetricsConfig) -> Result<()> {
+    let metrics = read_metrics(config)?;
+
+    if !metrics.is_empty() {
+        client.write_metrics(metrics).await?;
+    }
+
+    Ok(())
+}
+
+/// Collect one interval of metrics with a shared timestamp and send them to all sinks
+async fn monitor_cycle(
+    smc: &mut Option<SmcClient>,
+    client: Option<&InfluxClient>,
+    config: &MetricsConfig,
+    csv: Option<&mut CsvWriter>,
+    state: Option<&mut StateFile>,
+    stdout_json: bool,
+) -> Result<()> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| Error::Config(e.into()))?
+        .as_nanos() as u64;
+    let smc = live_smc(smc)?;
+    let platform = smc.key_platform();
+    let metrics: Vec<Metric> = collect_metrics(smc, platform, config)?
+        .into_iter()
+        .map(|metric| metric.timestamp(timestamp))
+        .collect();
+
+    if let Some(csv) = csv {
+        csv.write_interval(timestamp, &metrics)?;
+    }
+
+    if let Some(state) = state {
+        state.update(&metrics)?;
+    }
+
+    if stdout_json {
+        let record = serde_json::json!({ "timestamp": timestamp, "metrics": &metrics });
+        println!("{}", record);
+    }
+
+    if let Some(client) = client {
+        if !metrics.is_empty() {
+            client.write_metrics(metrics).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reuse the monitor's SMC connection, reconnecting first if it went stale (e.g. after sleep)
+fn live_smc(smc: &mut Option<SmcClient>) -> Result<&mut SmcClient> {
+    let client = match smc.take() {
+        Some(mut client) => {
+            if !client.is_alive() {
+                client.reconnect()?;
+            }
+            client
+        }
+        None => macsmc::connect()?,
+    };
+    Ok(smc.insert(client))
+}
+
+fn read_metrics(config: &MetricsConfig) -> Result<Vec<Metric>> {
     let mut smc_client = macsmc::connect()?;
+    let platform = smc_client.key_platform();
+    collect_metrics(&mut smc_client, platform, config)
+}
+
+fn collect_metrics(
+    source: &mut dyn SensorSource,
+    platform: Platform,
+    config: &MetricsConfig,
+) -> Result<Vec<Metric>> {
     let mut metrics = Vec::new();
 
     let hostname = hostname::get()
@@ -292,17 +630,13 @@ async fn send_to_influx_with_config(client: &InfluxClient, config: &MetricsConfi
 
     // CPU temperatures
     if config.cpu_temp {
-        // M2 CPU cores
-        for key in m2_cpu_temperature_keys() {
-            if let Ok(data) = smc_client.read_key(key.key) {
-                if let Ok(temp) = data.as_temperature() {
-                    let metric = Metric::new("cpu_temperature", "value", *temp as f64)
-                        .tag("host", &hostname)
-                        .tag("core", &extract_core_name(key.name))
-                        .tag("type", &extract_core_type(key.name));
-                    metrics.push(metric);
-                }
-            }
+        // CPU cores
+        for (kind, index, temp) in macsmc::source::labeled_cpu_core_temps(source, platform)? {
+            let metric = Metric::new("cpu_temperature", "value", *temp as f64)
+                .tag("host", &hostname)
+                .tag("core", &format!("{}_{}", kind, index))
+                .tag("type", &kind.to_string());
+            metrics.push(metric);
         }
 
         // Universal CPU temperatures (can add more if needed)
@@ -311,14 +645,12 @@ async fn send_to_influx_with_config(client: &InfluxClient, config: &MetricsConfi
 
     // GPU temperatures
     if config.gpu_temp {
-        for key in m2_gpu_temperature_keys() {
-            if let Ok(data) = smc_client.read_key(key.key) {
-                if let Ok(temp) = data.as_temperature() {
-                    let metric = Metric::new("gpu_temperature", "value", *temp as f64)
-                        .tag("host", &hostname)
-                        .tag("gpu", &extract_gpu_number(key.name));
-                    metrics.push(metric);
-                }
+        for key in gpu_temperature_keys(platform) {
+            if let Ok(temp) = macsmc::source::read_temperature(source, key) {
+                let metric = Metric::new("gpu_temperature", "value", *temp as f64)
+                    .tag("host", &hostname)
+                    .tag("gpu", &extract_gpu_number(key.name));
+                metrics.push(metric);
             }
         }
     }
@@ -326,32 +658,26 @@ async fn send_to_influx_with_config(client: &InfluxClient, config: &MetricsConfi
     // System temperatures
     if config.system_temp {
         for key in system_temperature_keys() {
-            if let Ok(data) = smc_client.read_key(key.key) {
-                if let Ok(temp) = data.as_temperature() {
-                    let metric = Metric::new("system_temperature", "value", *temp as f64)
-                        .tag("host", &hostname)
-                        .tag("sensor", &key.name.to_lowercase().replace(' ', "_"));
-                    metrics.push(metric);
-                }
+            if let Ok(temp) = macsmc::source::read_temperature(source, key) {
+                let metric = Metric::new("system_temperature", "value", *temp as f64)
+                    .tag("host", &hostname)
+                    .tag("sensor", &key.name.to_lowercase().replace(' ', "_"));
+                metrics.push(metric);
             }
         }
     }
 
     // Power metrics
     if config.power {
-        for key in power_keys() {
-            if let Ok(data) = smc_client.read_key(key.key) {
-                if let Ok(power) = data.as_power() {
-                    let metric = Metric::new("power", "value", *power as f64)
-                        .tag("host", &hostname)
-                        .tag("component", &key.name.to_lowercase().replace(' ', "_"));
-                    metrics.push(metric);
-                }
-            }
+        for (key, power) in macsmc::source::power_sensors(source, config.skip_zero_power) {
+            let metric = Metric::new("power", "value", *power as f64)
+                .tag("host", &hostname)
+                .tag("component", &key.name.to_lowercase().replace(' ', "_"));
+            metrics.push(metric);
         }
 
         // Voltage
-        if let Ok(data) = smc_client.read_key("VD0R") {
+        if let Ok(data) = source.read("VD0R") {
             if let Ok(voltage) = data.as_voltage() {
                 let metric = Metric::new("voltage", "value", *voltage as f64)
                     .tag("host", &hostname)
@@ -361,139 +687,285 @@ async fn send_to_influx_with_config(client: &InfluxClient, config: &MetricsConfi
         }
     }
 
-    // Fan speeds
-    if config.fans {
-        if let Ok(data) = smc_client.read_key("F0Ac") {
-            if let Ok(rpm) = data.as_rpm() {
-                let metric = Metric::new("fan_speed", "value", *rpm as f64)
+    // Voltage rails
+    if config.voltage {
+        for (key, voltage) in macsmc::source::voltages(source) {
+            let metric = Metric::new("voltage", "value", *voltage as f64)
+                .tag("host", &hostname)
+                .tag("rail", &key.name.to_lowercase().replace(' ', "_"));
+            metrics.push(metric);
+        }
+    }
+
+    // Currents
+    if config.current {
+        for key in current_keys() {
+            if let Ok(current) = source.read(key.key).and_then(|data| data.as_current()) {
+                let metric = Metric::new("current", "value", *current as f64)
                     .tag("host", &hostname)
-                    .tag("fan", "0");
+                    .tag("rail", &key.name.to_lowercase().replace(' ', "_"));
                 metrics.push(metric);
             }
         }
     }
 
-    if !metrics.is_empty() {
-        client.write_metrics(metrics).await?;
+    // Derived metrics, skipped individually so one bad expression doesn't drop the rest
+    for (name, expression) in &config.derived {
+        match derived::evaluate(expression, source) {
+            Ok(value) => {
+                let metric = Metric::new("derived", "value", value)
+                    .tag("host", &hostname)
+                    .tag("name", name);
+                metrics.push(metric);
+            }
+            Err(e) => eprintln!("Skipping derived metric {}: {}", name, e),
+        }
     }
 
-    Ok(())
-}
+    // Fan speeds, of all fans the SMC reports unless specific ones are configured
+    if config.fans {
+        let fans: Vec<u8> = match &config.fan_indices {
+            Some(indices) => indices.clone(),
+            None => {
+                let count = macsmc::source::fan_count(source).unwrap_or(1);
+                (0..count).collect()
+            }
+        };
 
-fn extract_core_name(name: &str) -> String {
-    if name.contains("Efficiency") {
-        "efficiency".to_string()
-    } else if let Some(num) = name.chars().last() {
-        if num.is_ascii_digit() {
-            format!("performance_{}", num)
-        } else {
-            "performance".to_string()
+        for fan in fans {
+            if let Ok(rpm) = macsmc::source::fan_actual_speed(source, fan) {
+                let metric = Metric::new("fan_speed", "value", *rpm as f64)
+                    .tag("host", &hostname)
+                    .tag("fan", &fan.to_string());
+                metrics.push(metric);
+            }
         }
-    } else {
-        "unknown".to_string()
     }
-}
 
-fn extract_core_type(name: &str) -> String {
-    if name.contains("Efficiency") {
-        "efficiency".to_string()
-    } else if name.contains("Performance") {
-        "performance".to_string()
-    } else {
-        "unknown".to_string()
-    }
+    Ok(metrics)
 }
 
+// Unnumbered sensors (the Intel discrete and integrated GPUs) are tagged by
+// name so they don't overwrite each other
 fn extract_gpu_number(name: &str) -> String {
     name.chars()
         .last()
         .filter(|c| c.is_ascii_digit())
         .map(|c| c.to_string())
-        .unwrap_or_else(|| "0".to_string())
+        .unwrap_or_else(|| name.to_lowercase().replace(' ', "_"))
 }
 
-fn print_cpu_info(client: &mut SmcClient) -> Result<()> {
-    println!("=== CPU Information ===");
-    println!();
-    
-    for key in m2_cpu_temperature_keys() {
-        if let Ok(data) = client.read_key(key.key) {
-            if let Ok(temp) = data.as_temperature() {
-                println!("{:>24}: {}", key.name, temp);
-            }
+/// A sensor reading ready for display
+struct Reading {
+    name: String,
+    key: String,
+    value: f32,
+    display: String,
+}
+
+impl Reading {
+    fn new(name: &str, key: &str, value: impl Deref<Target = f32> + Display) -> Self {
+        Self {
+            name: name.to_string(),
+            key: key.to_string(),
+            value: *value,
+            display: value.to_string(),
         }
     }
+}
+
+fn print_readings(mut readings: Vec<Reading>, sort: Option<SortBy>) {
+    match sort {
+        Some(SortBy::Name) => readings.sort_by(|a, b| a.name.cmp(&b.name)),
+        Some(SortBy::Key) => readings.sort_by(|a, b| a.key.cmp(&b.key)),
+        Some(SortBy::Value) => readings.sort_by(|a, b| b.value.total_cmp(&a.value)),
+        None => {}
+    }
+
+    for reading in readings {
+        println!("{:>24}: {}", reading.name, reading.display);
+    }
+}
+
+fn temperature_readings(client: &mut SmcClient, keys: Vec<&'static SmcKey>) -> Vec<Reading> {
+    let mut readings = Vec::new();
+    for key in keys {
+        if let Ok(temp) = macsmc::source::read_temperature(client, key) {
+            readings.push(Reading::new(key.name, key.key, temp));
+        }
+    }
+    readings
+}
+
+fn print_cpu_info(client: &mut SmcClient, sort: Option<SortBy>) -> Result<()> {
+    println!("=== CPU Information ===");
+    println!();
+
+    let keys = client.cpu_temperature_keys();
+    print_readings(temperature_readings(client, keys), sort);
     Ok(())
 }
 
-fn print_gpu_info(client: &mut SmcClient) -> Result<()> {
+fn print_gpu_info(client: &mut SmcClient, sort: Option<SortBy>) -> Result<()> {
     println!("=== GPU Information ===");
     println!();
-    
-    for key in m2_gpu_temperature_keys() {
-        if let Ok(data) = client.read_key(key.key) {
-            if let Ok(temp) = data.as_temperature() {
-                println!("{:>24}: {}", key.name, temp);
-            }
-        }
-    }
+
+    let keys = client.gpu_temperature_keys();
+    print_readings(temperature_readings(client, keys), sort);
     Ok(())
 }
 
-fn print_system_info(client: &mut SmcClient) -> Result<()> {
+fn print_system_info(client: &mut SmcClient, sort: Option<SortBy>) -> Result<()> {
     println!("=== System Information ===");
-    
-    for key in system_temperature_keys() {
-        if let Ok(data) = client.read_key(key.key) {
-            if let Ok(temp) = data.as_temperature() {
-                println!("{:>24}: {}", key.name, temp);
-            }
-        }
-    }
+
+    print_readings(temperature_readings(client, system_temperature_keys()), sort);
     Ok(())
 }
 
-fn print_power_info(client: &mut SmcClient) -> Result<()> {
+fn print_power_info(client: &mut SmcClient, sort: Option<SortBy>) -> Result<()> {
     println!("=== Power Information ===");
-    
-    for key in power_keys() {
-        if let Ok(data) = client.read_key(key.key) {
-            if let Ok(power) = data.as_power() {
-                println!("{:>24}: {}", key.name, power);
-            }
-        }
-    }
+
+    let mut readings: Vec<Reading> = client
+        .power_sensors(false)
+        .into_iter()
+        .map(|(key, power)| Reading::new(key.name, key.key, power))
+        .collect();
 
     // Voltage
     if let Ok(data) = client.read_key("VD0R") {
         if let Ok(voltage) = data.as_voltage() {
-            println!("{:>24}: {}", "DC In", voltage);
+            readings.push(Reading::new("DC In", "VD0R", voltage));
         }
     }
 
+    print_readings(readings, sort);
     Ok(())
 }
 
-fn print_fan_info(client: &mut SmcClient) -> Result<()> {
+fn print_fan_info(client: &mut SmcClient, sort: Option<SortBy>) -> Result<()> {
     println!("=== Fan Information ===");
-    
-    if let Ok(data) = client.read_key("F0Ac") {
-        if let Ok(rpm) = data.as_rpm() {
-            println!("{:>24}: {}", "Fan Speed", rpm);
+
+    let mut readings = Vec::new();
+    let mut stalled = Vec::new();
+    // Machines without `FNum` may still have a fan, so try the first one as collection does
+    for fan in 0..client.fan_count().unwrap_or(1) {
+        if let Ok(speed) = client.fan_speed(fan) {
+            let name = format!("Fan {} Speed", fan);
+            readings.push(Reading::new(&name, &FanKeys::new(fan).actual, speed.actual));
+            if speed.is_stalled() {
+                stalled.push((fan, speed));
+            }
         }
     }
+
+    print_readings(readings, sort);
+    for (fan, speed) in stalled {
+        println!(
+            "⚠ Fan {} appears stalled: {} while targeting {}",
+            fan, speed.actual, speed.target
+        );
+    }
     Ok(())
 }
 
-fn print_all_info(client: &mut SmcClient) -> Result<()> {
-    print_cpu_info(client)?;
+fn print_all_info(client: &mut SmcClient, sort: Option<SortBy>) -> Result<()> {
+    print_cpu_info(client, sort)?;
     println!();
-    print_gpu_info(client)?;
+    print_gpu_info(client, sort)?;
     println!();
-    print_system_info(client)?;
+    print_system_info(client, sort)?;
     println!();
-    print_power_info(client)?;
+    print_power_info(client, sort)?;
     println!();
-    print_fan_info(client)?;
+    print_fan_info(client, sort)?;
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macsmc::SmcData;
+
+    struct FakeSource(HashMap<&'static str, SmcData>);
+
+    impl SensorSource for FakeSource {
+        fn read(&mut self, key: &str) -> macsmc::Result<SmcData> {
+            self.0
+                .get(key)
+                .cloned()
+                .ok_or_else(|| SmcError::KeyNotFound(key.to_string()))
+        }
+    }
+
+    fn three_fans() -> FakeSource {
+        FakeSource(
+            [
+                ("FNum", SmcData::Uint(3)),
+                ("F0Ac", SmcData::Float(1000.0)),
+                ("F1Ac", SmcData::Float(1100.0)),
+                ("F2Ac", SmcData::Float(1200.0)),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+        )
+    }
+
+    fn fan_metrics(fan_indices: Option<Vec<u8>>) -> Vec<(String, f64)> {
+        let config = MetricsConfig {
+            fans: true,
+            fan_indices,
+            ..MetricsConfig::none()
+        };
+        collect_metrics(&mut three_fans(), Platform::M2, &config)
+            .unwrap()
+            .into_iter()
+            .map(|metric| (metric.tags["fan"].clone(), metric.value))
+            .collect()
+    }
+
+    #[test]
+    fn collects_every_fan_by_default() {
+        assert_eq!(
+            fan_metrics(None),
+            [
+                ("0".to_string(), 1000.0),
+                ("1".to_string(), 1100.0),
+                ("2".to_string(), 1200.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn collects_only_selected_fans() {
+        assert_eq!(fan_metrics(Some(vec![1])), [("1".to_string(), 1100.0)]);
+    }
+
+    fn parse(args: &[&str]) -> std::result::Result<Option<Commands>, clap::Error> {
+        Cli::try_parse_from(args).map(|cli| cli.command)
+    }
+
+    #[test]
+    fn fan_command_takes_exactly_one_setting() {
+        assert!(parse(&["macstats", "fan", "0"]).is_err());
+        assert!(parse(&["macstats", "fan", "0", "--rpm", "2000", "--percent", "50"]).is_err());
+        assert!(parse(&["macstats", "fan", "0", "--auto", "--mode", "forced"]).is_err());
+        assert!(parse(&["macstats", "fan", "0", "--mode", "sideways"]).is_err());
+
+        assert!(matches!(
+            parse(&["macstats", "fan", "1", "--rpm", "2000"]),
+            Ok(Some(Commands::Fan { index: 1, rpm: Some(2000), .. }))
+        ));
+        assert!(matches!(
+            parse(&["macstats", "fan", "0", "--auto"]),
+            Ok(Some(Commands::Fan { auto: true, mode: None, .. }))
+        ));
+    }
+
+    #[test]
+    fn fan_control_requires_root() {
+        assert!(require_root(0).is_ok());
+        let err = require_root(501).unwrap_err();
+        assert!(err.to_string().contains("sudo"));
+    }
+}
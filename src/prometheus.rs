@@ -0,0 +1,154 @@
+//! Prometheus text exposition output for `macstats prometheus`
+
+use crate::influx::Metric;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::Path,
+};
+
+/// Prefix of every metric name, matching the default InfluxDB measurement prefix
+const PREFIX: &str = "mac";
+
+/// Format metrics as Prometheus text exposition, one gauge per measurement
+///
+/// `labels` are added to every sample and overridden by the metric's own
+/// tags. Timestamps are left out so Prometheus uses the scrape time.
+pub fn format_exposition(metrics: &[Metric], labels: &HashMap<String, String>) -> String {
+    // Samples of a metric must be listed together, under its HELP and TYPE lines
+    let mut families: BTreeMap<String, (&str, Vec<String>)> = BTreeMap::new();
+
+    for metric in metrics {
+        let mut all_labels: BTreeMap<&str, &str> = labels
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        all_labels.extend(metric.tags.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+
+        let name = metric_name(metric);
+        let mut sample = name.clone();
+        if !all_labels.is_empty() {
+            let label_string: Vec<String> = all_labels
+                .iter()
+                .map(|(k, v)| format!("{}=\"{}\"", sanitize_name(k), escape_label_value(v)))
+                .collect();
+            sample.push('{');
+            sample.push_str(&label_string.join(","));
+            sample.push('}');
+        }
+        sample.push(' ');
+        sample.push_str(&format_value(metric.value));
+
+        families
+            .entry(name)
+            .or_insert_with(|| (&metric.measurement, Vec::new()))
+            .1
+            .push(sample);
+    }
+
+    let mut output = String::new();
+    for (name, (measurement, samples)) in families {
+        output.push_str(&format!("# HELP {} macstats {}\n", name, measurement));
+        output.push_str(&format!("# TYPE {} gauge\n", name));
+        for sample in samples {
+            output.push_str(&sample);
+            output.push('\n');
+        }
+    }
+    output
+}
+
+/// Write the exposition to `path` in one go, for node_exporter's textfile collector
+///
+/// The collector may read the file at any time, so it is written next to it
+/// and renamed into place.
+pub fn write_atomic(path: &Path, content: &str) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, path)
+}
+
+/// `mac_<measurement>`, with the field appended unless it is the usual `value`
+fn metric_name(metric: &Metric) -> String {
+    let name = if metric.field == "value" {
+        format!("{}_{}", PREFIX, metric.measurement)
+    } else {
+        format!("{}_{}_{}", PREFIX, metric.measurement, metric.field)
+    };
+    sanitize_name(&name)
+}
+
+// Metric and label names may only contain [a-zA-Z0-9_] and not start with a digit
+fn sanitize_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_samples_under_one_header() {
+        let metrics = [
+            Metric::new("cpu_temperature", "value", 45.5).tag("core", "1"),
+            Metric::new("fan_speed", "value", 1200.0).tag("fan", "0"),
+            Metric::new("cpu_temperature", "value", 50.0).tag("core", "2"),
+        ];
+        let labels: HashMap<String, String> =
+            [("host".to_string(), "mac".to_string())].iter().cloned().collect();
+
+        assert_eq!(
+            format_exposition(&metrics, &labels),
+            "# HELP mac_cpu_temperature macstats cpu_temperature\n\
+             # TYPE mac_cpu_temperature gauge\n\
+             mac_cpu_temperature{core=\"1\",host=\"mac\"} 45.5\n\
+             mac_cpu_temperature{core=\"2\",host=\"mac\"} 50\n\
+             # HELP mac_fan_speed macstats fan_speed\n\
+             # TYPE mac_fan_speed gauge\n\
+             mac_fan_speed{fan=\"0\",host=\"mac\"} 1200\n"
+        );
+    }
+
+    #[test]
+    fn metric_tags_override_labels() {
+        let metrics = [Metric::new("power", "value", 1.0).tag("host", "tagged")];
+        let labels: HashMap<String, String> =
+            [("host".to_string(), "label".to_string())].iter().cloned().collect();
+        assert!(format_exposition(&metrics, &labels).contains("mac_power{host=\"tagged\"} 1\n"));
+    }
+
+    #[test]
+    fn names_and_values_are_escaped() {
+        let metrics = [Metric::new("fan", "max-speed", f64::NAN).tag("1st label", "a\"b\\c\nd")];
+        let output = format_exposition(&metrics, &HashMap::new());
+        assert!(output.contains("mac_fan_max_speed{_1st_label=\"a\\\"b\\\\c\\nd\"} NaN\n"));
+        assert_eq!(format_value(f64::INFINITY), "+Inf");
+        assert_eq!(format_value(f64::NEG_INFINITY), "-Inf");
+    }
+}
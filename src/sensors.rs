@@ -0,0 +1,148 @@
+//! User-provided sensor key definitions for macstats
+//!
+//! Keys listed in `sensors.toml` next to `config.toml` are added to the built-in
+//! key lists, so newly discovered keys can be used without recompiling:
+//!
+//! ```toml
+//! [[sensors]]
+//! key = "Tp0Z"
+//! name = "CPU Performance Core 9"
+//! category = "cpu"          # cpu, gpu, system, sensor, fan, battery, power
+//! type = "temperature"      # temperature, voltage, current, power, fan_speed, ...
+//! platforms = ["M2 Pro"]    # optional, cpu and gpu temperatures only
+//! scale = 0.1               # optional factor from raw value to unit
+//! ```
+
+use macsmc::{
+    keys::{register_key, KeyCategory, KeyDataType, SmcKey},
+    Platform,
+};
+use serde::Deserialize;
+use std::{error::Error as StdError, fs, path::PathBuf};
+
+#[derive(Debug, Deserialize)]
+struct SensorsFile {
+    #[serde(default)]
+    sensors: Vec<SensorOverride>,
+}
+
+/// A single user-provided sensor definition
+#[derive(Debug, Clone, Deserialize)]
+pub struct SensorOverride {
+    /// 4-character SMC key
+    pub key: String,
+    /// Human-readable name
+    pub name: String,
+    /// Key category
+    pub category: String,
+    /// Expected data type
+    #[serde(rename = "type")]
+    pub data_type: String,
+    /// Platforms the key applies to (all if missing), for CPU and GPU temperatures only
+    pub platforms: Option<Vec<String>>,
+    /// Factor converting the raw value into the unit (default conversion if missing)
+    pub scale: Option<f32>,
+}
+
+impl SensorOverride {
+    /// Validate the definition and convert it into an SMC key with its platforms
+    pub fn validate(&self) -> Result<(SmcKey, Option<Vec<Platform>>), String> {
+        if self.key.len() != 4 || !self.key.is_ascii() {
+            return Err(format!("key must be 4 ASCII characters, got {:?}", self.key));
+        }
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+
+        let category: KeyCategory = self.category.parse()?;
+        let data_type: KeyDataType = self.data_type.parse()?;
+        if let Some(scale) = self.scale {
+            if !scale.is_finite() || scale == 0.0 {
+                return Err(format!("scale must be a finite, non-zero number, got {}", scale));
+            }
+        }
+        let platforms = self
+            .platforms
+            .as_ref()
+            .map(|names| names.iter().map(|name| name.parse()).collect())
+            .transpose()?;
+        // Only the CPU and GPU temperature lists are picked per platform
+        let per_platform = data_type == KeyDataType::Temperature
+            && matches!(category, KeyCategory::Cpu | KeyCategory::Gpu);
+        if platforms.is_some() && !per_platform {
+            return Err("platforms can only be set for cpu and gpu temperature keys".to_string());
+        }
+
+        let key = SmcKey::new(leak(&self.key), leak(&self.name), category, data_type)
+            .with_scale(self.scale.unwrap_or(1.0));
+        Ok((key, platforms))
+    }
+}
+
+// Overrides are loaded once and used for the whole process
+fn leak(s: &str) -> &'static str {
+    Box::leak(s.to_string().into_boxed_str())
+}
+
+/// Get the sensor overrides file path
+pub fn sensors_path() -> Result<PathBuf, Box<dyn StdError>> {
+    let config_dir = dirs::config_dir().ok_or("Cannot determine config directory")?;
+    Ok(config_dir.join("macstats").join("sensors.toml"))
+}
+
+/// Load and register the sensor overrides, returning how many were added
+///
+/// Invalid entries are reported on stderr and skipped.
+pub fn load_overrides() -> Result<usize, Box<dyn StdError>> {
+    let path = sensors_path()?;
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    let content = fs::read_to_string(&path)?;
+    let file: SensorsFile = toml::from_str(&content)?;
+
+    let mut registered = 0;
+    for sensor in file.sensors {
+        match sensor.validate() {
+            Ok((key, platforms)) => {
+                register_key(key, platforms);
+                registered += 1;
+            }
+            Err(e) => eprintln!("Ignoring sensor override {:?}: {}", sensor.key, e),
+        }
+    }
+
+    Ok(registered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sensor(category: &str, data_type: &str, platforms: Option<&[&str]>) -> SensorOverride {
+        SensorOverride {
+            key: "Tp0Z".to_string(),
+            name: "Test Sensor".to_string(),
+            category: category.to_string(),
+            data_type: data_type.to_string(),
+            platforms: platforms.map(|names| names.iter().map(|name| name.to_string()).collect()),
+            scale: None,
+        }
+    }
+
+    #[test]
+    fn platforms_accepted_for_cpu_and_gpu_temperatures() {
+        let (_, platforms) = sensor("cpu", "temperature", Some(&["M2 Pro"])).validate().unwrap();
+        assert_eq!(platforms, Some(vec![Platform::M2Pro]));
+        assert!(sensor("gpu", "temperature", Some(&["M3"])).validate().is_ok());
+    }
+
+    #[test]
+    fn platforms_rejected_where_they_would_be_ignored() {
+        assert!(sensor("system", "temperature", Some(&["M2"])).validate().is_err());
+        assert!(sensor("power", "power", Some(&["M2"])).validate().is_err());
+        assert!(sensor("cpu", "voltage", Some(&["M2"])).validate().is_err());
+        assert!(sensor("power", "power", None).validate().is_ok());
+    }
+}
@@ -0,0 +1,105 @@
+//! Persisted session peaks for `macstats monitor --state-file`
+
+use crate::influx::Metric;
+use macsmc::MinMax;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Minimum and maximum of every metric, kept in a JSON file across runs
+pub struct StateFile {
+    path: PathBuf,
+    peaks: BTreeMap<String, MinMax<f64>>,
+}
+
+impl StateFile {
+    /// Load the peaks from `path`, starting empty if the file doesn't exist yet
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let peaks = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            peaks,
+        })
+    }
+
+    /// Fold one interval of metrics into the peaks and save the file
+    ///
+    /// Non-finite values are skipped.
+    pub fn update(&mut self, metrics: &[Metric]) -> io::Result<()> {
+        for metric in metrics {
+            let value = metric.value;
+            // JSON has no NaN or infinity, so the file could not be read back
+            if !value.is_finite() {
+                continue;
+            }
+            self.peaks
+                .entry(series_key(metric))
+                .and_modify(|peak| peak.update(value))
+                .or_insert_with(|| MinMax::new(value));
+        }
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        // Write next to the file and rename, so a crash never leaves it half written
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&self.peaks)?)?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+/// Measurement and sorted tags, e.g. `cpu_temperature,core=1,host=mac`
+fn series_key(metric: &Metric) -> String {
+    let mut tags: Vec<String> = metric
+        .tags
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect();
+    tags.sort();
+
+    let mut key = metric.measurement.clone();
+    for tag in tags {
+        key.push(',');
+        key.push_str(&tag);
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn update_skips_non_finite_and_round_trips() {
+        let path = env::temp_dir().join(format!("macstats-state-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut state = StateFile::open(&path).unwrap();
+        state
+            .update(&[
+                Metric::new("cpu_temperature", "value", 50.0).tag("core", "1"),
+                Metric::new("cpu_temperature", "value", f64::NAN).tag("core", "2"),
+            ])
+            .unwrap();
+        state
+            .update(&[
+                Metric::new("cpu_temperature", "value", f64::INFINITY).tag("core", "1"),
+                Metric::new("cpu_temperature", "value", 70.0).tag("core", "1"),
+            ])
+            .unwrap();
+
+        let reopened = StateFile::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(reopened.peaks.len(), 1);
+        let peak = reopened.peaks["cpu_temperature,core=1"];
+        assert_eq!((peak.min, peak.max, peak.current), (50.0, 70.0, 70.0));
+    }
+}